@@ -0,0 +1,292 @@
+use crate::tools::Tools;
+use std::ffi::OsString;
+use std::path::Path;
+
+// 真峰值与响度范围使用 EBU R128 推荐值
+const TRUE_PEAK: f64 = -1.0;
+const LOUDNESS_RANGE: f64 = 7.0;
+
+// 声道缩混
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Downmix {
+    #[default]
+    Keep,
+    Stereo,
+    Mono,
+}
+
+impl Downmix {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "keep" => Some(Downmix::Keep),
+            "stereo" => Some(Downmix::Stereo),
+            "mono" => Some(Downmix::Mono),
+            _ => None,
+        }
+    }
+
+    fn layout(self) -> Option<&'static str> {
+        match self {
+            Downmix::Keep => None,
+            Downmix::Stereo => Some("stereo"),
+            Downmix::Mono => Some("mono"),
+        }
+    }
+}
+
+// 合并时对音频流的处理，启用任意一项时音频重新编码，视频仍为流复制
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioFilters {
+    // 两遍 loudnorm 响度标准化
+    pub normalize: bool,
+    pub target_lufs: f64,
+    // 音量增益（dB），标准化时不生效
+    pub gain_db: f64,
+    pub downmix: Downmix,
+}
+
+impl Default for AudioFilters {
+    fn default() -> Self {
+        Self {
+            normalize: false,
+            target_lufs: -23.0,
+            gain_db: 0.0,
+            downmix: Downmix::Keep,
+        }
+    }
+}
+
+// 第一遍 loudnorm 的测量结果
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Measured {
+    pub input_i: f64,
+    pub input_tp: f64,
+    pub input_lra: f64,
+    pub input_thresh: f64,
+    pub target_offset: f64,
+}
+
+impl AudioFilters {
+    pub fn is_empty(&self) -> bool {
+        !self.normalize && self.gain_db == 0.0 && self.downmix == Downmix::Keep
+    }
+
+    // 标准化之前的滤镜，测量与最终处理共用
+    fn pre_filters(&self) -> Vec<String> {
+        let mut filters = Vec::new();
+        if let Some(layout) = self.downmix.layout() {
+            filters.push(format!("aformat=channel_layouts={}", layout));
+        }
+        if !self.normalize && self.gain_db != 0.0 {
+            filters.push(format!("volume={}dB", self.gain_db));
+        }
+        filters
+    }
+
+    fn loudnorm(&self) -> String {
+        format!(
+            "loudnorm=I={}:TP={}:LRA={}",
+            self.target_lufs, TRUE_PEAK, LOUDNESS_RANGE
+        )
+    }
+
+    // 完整滤镜链，标准化时需要传入第一遍的测量结果
+    pub fn filter_chain(&self, measured: Option<&Measured>) -> Option<String> {
+        let mut filters = self.pre_filters();
+        if let Some(m) = measured.filter(|_| self.normalize) {
+            filters.push(format!(
+                "{}:measured_I={}:measured_TP={}:measured_LRA={}:measured_thresh={}:offset={}:linear=true",
+                self.loudnorm(),
+                m.input_i,
+                m.input_tp,
+                m.input_lra,
+                m.input_thresh,
+                m.target_offset
+            ));
+            // loudnorm 内部上采样到 192kHz，输出前恢复常用采样率
+            filters.push("aresample=48000".to_string());
+        }
+        (!filters.is_empty()).then(|| filters.join(","))
+    }
+}
+
+// 从 print_format=json 的输出中读取 "key" : "value"
+fn json_value(text: &str, key: &str) -> Option<f64> {
+    let rest = &text[text.find(&format!("\"{}\"", key))? + key.len() + 2..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start();
+    let rest = rest.strip_prefix('"')?;
+    rest[..rest.find('"')?].trim().parse().ok()
+}
+
+fn parse_measured(stderr: &str) -> Option<Measured> {
+    // JSON 块位于输出末尾
+    let json = &stderr[stderr.rfind('{')?..];
+    Some(Measured {
+        input_i: json_value(json, "input_i")?,
+        input_tp: json_value(json, "input_tp")?,
+        input_lra: json_value(json, "input_lra")?,
+        input_thresh: json_value(json, "input_thresh")?,
+        target_offset: json_value(json, "target_offset")?,
+    })
+}
+
+// 第一遍：测量音频文件的第一条音频流
+pub fn measure(tools: &Tools, audio: &Path, filters: &AudioFilters) -> Result<Measured, String> {
+    let mut chain = filters.pre_filters();
+    chain.push(format!("{}:print_format=json", filters.loudnorm()));
+    let args: Vec<OsString> = vec![
+        "-hide_banner".into(),
+        "-nostats".into(),
+        "-i".into(),
+        audio.into(),
+        "-map".into(),
+        "0:a:0".into(),
+        "-af".into(),
+        chain.join(",").into(),
+        "-f".into(),
+        "null".into(),
+        "-".into(),
+    ];
+    let output = tools.execute_ffmpeg(&args, "响度测量")?;
+
+    let measured =
+        parse_measured(&String::from_utf8_lossy(&output.stderr)).ok_or("无法解析响度测量结果")?;
+    if !measured.input_i.is_finite() {
+        return Err("音频为静音，无法标准化响度".to_string());
+    }
+    Ok(measured)
+}
+
+// 需要时先测量，返回合并时使用的音频滤镜
+pub fn prepare(
+    tools: &Tools,
+    audio: &Path,
+    filters: &AudioFilters,
+) -> Result<Option<String>, String> {
+    let measured = if filters.normalize {
+        Some(measure(tools, audio, filters)?)
+    } else {
+        None
+    };
+    Ok(filters.filter_chain(measured.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{self, Reply, StubRunner};
+
+    const MEASURED: &str = "[Parsed_loudnorm_0 @ 0x55d]\n{\n\
+        \t\"input_i\" : \"-27.61\",\n\
+        \t\"input_tp\" : \"-4.47\",\n\
+        \t\"input_lra\" : \"18.06\",\n\
+        \t\"input_thresh\" : \"-39.20\",\n\
+        \t\"output_i\" : \"-16.58\",\n\
+        \t\"target_offset\" : \"0.58\"\n}\n";
+
+    #[test]
+    fn builds_filter_chains() {
+        assert_eq!(AudioFilters::default().filter_chain(None), None);
+
+        let gain = AudioFilters {
+            gain_db: -3.5,
+            downmix: Downmix::Stereo,
+            ..Default::default()
+        };
+        assert_eq!(
+            gain.filter_chain(None).as_deref(),
+            Some("aformat=channel_layouts=stereo,volume=-3.5dB")
+        );
+
+        // 标准化时忽略增益
+        let normalize = AudioFilters {
+            normalize: true,
+            gain_db: 6.0,
+            ..Default::default()
+        };
+        let measured = parse_measured(MEASURED).unwrap();
+        assert_eq!(
+            normalize.filter_chain(Some(&measured)).as_deref(),
+            Some(
+                "loudnorm=I=-23:TP=-1:LRA=7:measured_I=-27.61:measured_TP=-4.47:\
+                 measured_LRA=18.06:measured_thresh=-39.2:offset=0.58:linear=true,aresample=48000"
+            )
+        );
+    }
+
+    #[test]
+    fn parses_measurement() {
+        assert_eq!(
+            parse_measured(MEASURED),
+            Some(Measured {
+                input_i: -27.61,
+                input_tp: -4.47,
+                input_lra: 18.06,
+                input_thresh: -39.2,
+                target_offset: 0.58,
+            })
+        );
+        assert_eq!(parse_measured("no json here"), None);
+        assert_eq!(json_value(MEASURED, "missing"), None);
+    }
+
+    #[test]
+    fn measures_first_audio_stream() {
+        let runner = StubRunner::new(|_, _| Reply {
+            stderr: MEASURED.to_string(),
+            ..Default::default()
+        });
+        let filters = AudioFilters {
+            normalize: true,
+            downmix: Downmix::Mono,
+            ..Default::default()
+        };
+
+        let filter = prepare(&runner.tools(), Path::new("a.m4a"), &filters)
+            .unwrap()
+            .unwrap();
+        assert!(
+            filter.starts_with("aformat=channel_layouts=mono,loudnorm="),
+            "{}",
+            filter
+        );
+        let calls = stub::calls_to(&runner, "ffmpeg");
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].windows(2).any(|w| w == ["-map", "0:a:0"]));
+        assert!(calls[0].windows(2).any(|w| w
+            == [
+                "-af",
+                "aformat=channel_layouts=mono,loudnorm=I=-23:TP=-1:LRA=7:print_format=json"
+            ]));
+    }
+
+    #[test]
+    fn silent_audio_cannot_be_normalized() {
+        let runner = StubRunner::new(|_, _| Reply {
+            stderr: MEASURED.replace("-27.61", "-inf"),
+            ..Default::default()
+        });
+        let filters = AudioFilters {
+            normalize: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            measure(&runner.tools(), Path::new("a.m4a"), &filters).unwrap_err(),
+            "音频为静音，无法标准化响度"
+        );
+    }
+
+    #[test]
+    fn no_measurement_without_normalize() {
+        let runner = StubRunner::new(|_, _| Reply::default());
+        let filters = AudioFilters {
+            gain_db: 2.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            prepare(&runner.tools(), Path::new("a.m4a"), &filters).unwrap(),
+            Some("volume=2dB".to_string())
+        );
+        assert!(runner.calls().is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::ffmpeg::MergeArgs;
+use crate::probe;
+use crate::tools::Tools;
+use std::path::Path;
+
+// 校验合并结果：文件非空、可被 ffprobe 解析、时长与输入相符
+pub fn verify_output(tools: &Tools, args: &MergeArgs) -> Result<(), String> {
+    let size = std::fs::metadata(args.output())
+        .map_err(|e| format!("无法读取输出文件: {}", e))?
+        .len();
+    if size == 0 {
+        return Err("输出文件为空".to_string());
+    }
+
+    let output = probe::duration(tools, args.output()).ok_or("无法探测输出文件时长")?;
+    let video = probe::duration(tools, args.video()).ok_or("无法探测视频时长")?;
+    let audio = probe::duration(tools, args.audio()).ok_or("无法探测音频时长")?
+        + args.audio_delay_ms() as f64 / 1000.0;
+
+    let expected = if args.is_shortest() {
+        video.min(audio)
+    } else {
+        video.max(audio)
+    };
+    if (output - expected).abs() > probe::DURATION_TOLERANCE {
+        return Err(format!(
+            "输出时长 {:.1} 秒与预期 {:.1} 秒不符",
+            output, expected
+        ));
+    }
+
+    Ok(())
+}
+
+// 将文件移至系统回收站，返回所有失败项
+pub fn trash_files(tools: &Tools, paths: &[&Path]) -> Result<(), String> {
+    let failures: Vec<String> = paths
+        .iter()
+        .filter_map(|path| {
+            tools
+                .runner
+                .trash(path)
+                .err()
+                .map(|e| format!("{}: {}", path.display(), e))
+        })
+        .collect();
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(failures.join("; "))
+    }
+}
+
+// 校验通过后将源文件移至回收站，返回状态描述；校验或移动失败时返回错误
+pub fn remove_sources(tools: &Tools, args: &MergeArgs) -> Result<String, String> {
+    verify_output(tools, args).map_err(|e| format!("输出校验失败，已保留源文件: {}", e))?;
+    trash_files(tools, &[args.video(), args.audio()])
+        .map_err(|e| format!("移至回收站失败: {}", e))?;
+    Ok("源文件已移至回收站".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{self, Reply, StubRunner};
+    use crate::trim::WorkDir;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    // 按文件名返回时长的桩 ffprobe
+    fn tools_with_durations(output: f64, video: f64, audio: f64) -> Tools {
+        stub_with_durations(output, video, audio).tools()
+    }
+
+    fn stub_with_durations(output: f64, video: f64, audio: f64) -> Arc<StubRunner> {
+        StubRunner::new(move |_, args| {
+            let path = args.last().unwrap().to_string_lossy().to_string();
+            let duration = if path.ends_with("out.mp4") {
+                output
+            } else if path.ends_with(".m4a") {
+                audio
+            } else {
+                video
+            };
+            Reply::ok(&stub::probe_output(duration, &[("video", "h264")]))
+        })
+    }
+
+    fn sources(dir: &WorkDir) -> (PathBuf, PathBuf, PathBuf) {
+        let (video, audio, output) = (dir.join("v.mp4"), dir.join("a.m4a"), dir.join("out.mp4"));
+        std::fs::write(&video, "video").unwrap();
+        std::fs::write(&audio, "audio").unwrap();
+        (video, audio, output)
+    }
+
+    #[test]
+    fn keeps_sources_when_output_is_empty() {
+        let dir = WorkDir::create().unwrap();
+        let (video, audio, output) = sources(&dir);
+        std::fs::write(&output, "").unwrap();
+        let args = MergeArgs::new(&video, &audio, &output);
+
+        let message = remove_sources(&tools_with_durations(10.0, 10.0, 10.0), &args).unwrap_err();
+        assert!(message.contains("输出文件为空"), "{}", message);
+        assert!(video.exists() && audio.exists());
+    }
+
+    #[test]
+    fn keeps_sources_when_output_is_missing() {
+        let dir = WorkDir::create().unwrap();
+        let (video, audio, output) = sources(&dir);
+        let args = MergeArgs::new(&video, &audio, &output);
+
+        let message = remove_sources(&tools_with_durations(10.0, 10.0, 10.0), &args).unwrap_err();
+        assert!(message.starts_with("输出校验失败"), "{}", message);
+        assert!(video.exists() && audio.exists());
+    }
+
+    #[test]
+    fn keeps_sources_when_duration_differs() {
+        let dir = WorkDir::create().unwrap();
+        let (video, audio, output) = sources(&dir);
+        std::fs::write(&output, "merged").unwrap();
+        let args = MergeArgs::new(&video, &audio, &output);
+
+        let message = remove_sources(&tools_with_durations(5.0, 10.0, 10.0), &args).unwrap_err();
+        assert!(message.contains("与预期"), "{}", message);
+        assert!(video.exists() && audio.exists());
+    }
+
+    #[test]
+    fn trashes_sources_after_successful_verification() {
+        let dir = WorkDir::create().unwrap();
+        let (video, audio, output) = sources(&dir);
+        std::fs::write(&output, "merged").unwrap();
+        let args = MergeArgs::new(&video, &audio, &output);
+
+        let runner = stub_with_durations(10.0, 10.0, 10.0);
+        let message = remove_sources(&runner.tools(), &args).unwrap();
+        assert_eq!(message, "源文件已移至回收站");
+        assert_eq!(runner.trashed(), [video, audio]);
+        assert!(output.exists());
+    }
+
+    #[test]
+    fn reports_files_that_could_not_be_trashed() {
+        let dir = WorkDir::create().unwrap();
+        let (video, audio, output) = sources(&dir);
+        std::fs::write(&output, "merged").unwrap();
+        let args = MergeArgs::new(&video, &audio, &output);
+
+        let runner = stub_with_durations(10.0, 10.0, 10.0).trash_error("permission denied");
+        let message = remove_sources(&runner.tools(), &args).unwrap_err();
+        assert!(message.starts_with("移至回收站失败: "), "{}", message);
+        assert!(
+            message.contains(&format!("{}: permission denied", audio.display())),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn expected_duration_follows_delay_and_shortest() {
+        let dir = WorkDir::create().unwrap();
+        let (video, audio, output) = sources(&dir);
+        std::fs::write(&output, "merged").unwrap();
+
+        // 音频延迟 2 秒后共 12 秒
+        let args = MergeArgs::new(&video, &audio, &output).audio_delay(2000);
+        assert!(verify_output(&tools_with_durations(12.0, 10.0, 10.0), &args).is_ok());
+        assert!(verify_output(&tools_with_durations(10.0, 10.0, 10.0), &args).is_err());
+
+        // 按最短截断时以视频为准
+        let args = args.shortest(true);
+        assert!(verify_output(&tools_with_durations(10.0, 10.0, 10.0), &args).is_ok());
+    }
+}
@@ -0,0 +1,648 @@
+use crate::audio::{AudioFilters, Downmix};
+use crate::concat;
+use crate::encode::{self, Profile, TranscodeArgs};
+use crate::extract;
+use crate::ffmpeg::{Container, MergeArgs};
+use crate::job::{self, MergeJob};
+use crate::metadata::{self, Metadata, MetadataFile};
+use crate::naming::{self, Collision};
+use crate::probe::{self, StreamInfo};
+use crate::remux::{self, RemuxArgs};
+use crate::tools::Tools;
+use crate::trim::Segment;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+用法: ffmerge -v <视频> -a <音频> [-o <输出>] [--container mp4|mkv|mov]
+               [--name <模板>] [--on-exists suffix|overwrite|skip]
+               [--audio-delay <毫秒>] [--shortest] [--delete-sources]
+               [--loudnorm] [--loudness <LUFS>] [--gain <dB>]
+               [--downmix stereo|mono]
+               [--metadata <文件>] [--title <标题>] [--artist <艺术家>]
+               [--comment <备注>] [--cover <图片>]
+               [--keep <开始>-<结束>]... [--accurate]
+               [--ffmpeg <路径>] [--ffprobe <路径>]
+      ffmerge concat <片段>... [-o <输出>] [--on-exists ...]
+      ffmerge extract <文件> [--list] [--streams <序号,...>] [--on-exists ...]
+      ffmerge remux <文件>... --container mp4|mkv|mov [--faststart]
+                    [--keep-data] [--no-subtitles] [-o <输出>] [--on-exists ...]
+      ffmerge transcode <文件>... [--profile <预设>] [--crf <值>] [--container ...]
+                    [--dry-run] [-o <输出>] [--on-exists ...]
+
+子命令:
+  concat                    按顺序拼接多个片段，参数一致时无损拼接，否则重新编码
+  extract                   将文件中的流分别复制到单独的文件，默认提取全部流
+  remux                     不重新编码转换容器格式，默认去除数据流
+  transcode                 按预设重新编码，运行前显示完整命令行
+
+选项:
+  -v, --video <路径>        输入视频文件
+  -a, --audio <路径>        输入音频文件
+  -o, --output <路径>       输出文件，省略时输出到视频所在目录
+      --container <格式>    输出容器 (mp4, mkv, mov)
+      --name <模板>         省略输出时的命名模板，默认 {stem}.merged
+                            支持 {stem} {date} {resolution} {vcodec} {acodec}
+      --on-exists <方式>    输出已存在时: suffix 添加序号(默认), overwrite 覆盖, skip 跳过
+      --audio-delay <毫秒>  音频延迟，负数表示音频提前
+      --shortest            按最短的流截断输出
+      --delete-sources      校验成功后将源文件移至回收站
+      --loudnorm            两遍 loudnorm 响度标准化（EBU R128）
+      --loudness <LUFS>     标准化目标响度，默认 -23
+      --gain <dB>           音量增益，标准化时不生效
+      --downmix <声道>      缩混为 stereo 或 mono
+                            使用以上音频选项时音频重新编码为 AAC，视频仍为流复制
+      --metadata <文件>     从 ffmetadata 文件导入元数据与章节
+      --title <标题>        设置标题
+      --artist <艺术家>     设置艺术家
+      --comment <备注>      设置备注
+      --cover <图片>        附加封面图片
+      --keep <开始>-<结束>  只保留该片段，可多次指定，如 1:30-2:45 或 90-
+      --accurate            精确剪切：H.264/HEVC 只重新编码起点所在的 GOP，
+                            其他编码重新编码整个片段
+      --ffmpeg <路径>       指定 ffmpeg 可执行文件，默认自动检测
+      --ffprobe <路径>      指定 ffprobe 可执行文件，默认自动检测
+      --list                列出文件中的流（extract）
+      --streams <序号,...>  只提取指定序号的流（extract）
+      --faststart           将索引移到文件头，便于网页播放（remux）
+      --keep-data           保留数据流（remux）
+      --no-subtitles        去除字幕（remux）
+      --profile <预设>      转码预设，默认第一个（transcode）
+                            可写界面中保存的预设名称，或内置预设 h264, h265, av1, 720p
+      --crf <值>            覆盖预设中的 CRF（transcode）
+      --dry-run             只显示命令行，不执行（transcode）
+  -h, --help                显示帮助";
+
+#[derive(Debug, Default)]
+struct CliOptions {
+    video: Option<PathBuf>,
+    audio: Option<PathBuf>,
+    output: Option<PathBuf>,
+    container: Option<Container>,
+    name_template: Option<String>,
+    collision: Collision,
+    audio_delay_ms: i64,
+    shortest: bool,
+    delete_sources: bool,
+    audio_filters: AudioFilters,
+    metadata: Option<PathBuf>,
+    title: Option<String>,
+    artist: Option<String>,
+    comment: Option<String>,
+    cover: Option<PathBuf>,
+    segments: Vec<Segment>,
+    accurate: bool,
+    ffmpeg: Option<PathBuf>,
+    ffprobe: Option<PathBuf>,
+    list: bool,
+    faststart: bool,
+    keep_data: bool,
+    no_subtitles: bool,
+    profile: Option<String>,
+    crf: Option<u8>,
+    dry_run: bool,
+    streams: Option<Vec<u32>>,
+    // 子命令的位置参数
+    inputs: Vec<PathBuf>,
+    help: bool,
+}
+
+// 路径以外的参数必须是有效的 UTF-8 文本
+fn text(value: OsString) -> Result<String, String> {
+    value
+        .into_string()
+        .map_err(|v| format!("参数不是有效的文本: {}", v.to_string_lossy()))
+}
+
+fn parse(args: &[OsString]) -> Result<CliOptions, String> {
+    let mut opts = CliOptions::default();
+    let mut iter = args.iter();
+
+    // 路径参数保持 OsString，不要求是 UTF-8
+    while let Some(raw) = iter.next() {
+        let arg = raw.to_string_lossy();
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("选项 {} 缺少参数", name))
+        };
+
+        match arg.as_ref() {
+            "-v" | "--video" => opts.video = Some(value(&arg)?.into()),
+            "-a" | "--audio" => opts.audio = Some(value(&arg)?.into()),
+            "-o" | "--output" => opts.output = Some(value(&arg)?.into()),
+            "--container" => {
+                let name = text(value(&arg)?)?;
+                let container = Container::from_name(&name)
+                    .ok_or_else(|| format!("不支持的容器格式: {}", name))?;
+                opts.container = Some(container);
+            }
+            "--name" => opts.name_template = Some(text(value(&arg)?)?),
+            "--on-exists" => {
+                let name = text(value(&arg)?)?;
+                opts.collision = Collision::from_name(&name)
+                    .ok_or_else(|| format!("无效的处理方式: {}", name))?;
+            }
+            "--audio-delay" => {
+                let ms = text(value(&arg)?)?;
+                opts.audio_delay_ms = ms.parse().map_err(|_| format!("无效的音频延迟: {}", ms))?;
+            }
+            "--shortest" => opts.shortest = true,
+            "--delete-sources" => opts.delete_sources = true,
+            "--loudnorm" => opts.audio_filters.normalize = true,
+            "--loudness" => {
+                let lufs = text(value(&arg)?)?;
+                opts.audio_filters.target_lufs = lufs
+                    .parse()
+                    .ok()
+                    .filter(|l| (-70.0..=-5.0).contains(l))
+                    .ok_or_else(|| format!("无效的目标响度: {}", lufs))?;
+            }
+            "--gain" => {
+                let db = text(value(&arg)?)?;
+                opts.audio_filters.gain_db =
+                    db.parse().map_err(|_| format!("无效的增益: {}", db))?;
+            }
+            "--downmix" => {
+                let name = text(value(&arg)?)?;
+                opts.audio_filters.downmix =
+                    Downmix::from_name(&name).ok_or_else(|| format!("无效的声道: {}", name))?;
+            }
+            "--metadata" => opts.metadata = Some(value(&arg)?.into()),
+            "--title" => opts.title = Some(text(value(&arg)?)?),
+            "--artist" => opts.artist = Some(text(value(&arg)?)?),
+            "--comment" => opts.comment = Some(text(value(&arg)?)?),
+            "--cover" => opts.cover = Some(value(&arg)?.into()),
+            "--keep" => {
+                let spec = text(value(&arg)?)?;
+                let segment =
+                    Segment::parse(&spec).ok_or_else(|| format!("无效的片段: {}", spec))?;
+                opts.segments.push(segment);
+            }
+            "--accurate" => opts.accurate = true,
+            "--ffmpeg" => opts.ffmpeg = Some(value(&arg)?.into()),
+            "--ffprobe" => opts.ffprobe = Some(value(&arg)?.into()),
+            "--list" => opts.list = true,
+            "--faststart" => opts.faststart = true,
+            "--keep-data" => opts.keep_data = true,
+            "--no-subtitles" => opts.no_subtitles = true,
+            "--profile" => opts.profile = Some(text(value(&arg)?)?),
+            "--crf" => {
+                let crf = text(value(&arg)?)?;
+                opts.crf = Some(crf.parse().map_err(|_| format!("无效的 CRF: {}", crf))?);
+            }
+            "--dry-run" => opts.dry_run = true,
+            "--streams" => {
+                let list = text(value(&arg)?)?;
+                let streams = list
+                    .split(',')
+                    .map(|s| s.trim().parse().ok())
+                    .collect::<Option<Vec<u32>>>()
+                    .ok_or_else(|| format!("无效的流序号: {}", list))?;
+                opts.streams = Some(streams);
+            }
+            "-h" | "--help" => opts.help = true,
+            other if other.starts_with('-') => return Err(format!("未知参数: {}", other)),
+            _ => opts.inputs.push(raw.into()),
+        }
+    }
+
+    Ok(opts)
+}
+
+fn setup_tools(opts: &CliOptions) -> Result<Tools, String> {
+    let mut tools = Tools::detect();
+    if let Some(ffmpeg) = &opts.ffmpeg {
+        tools.ffmpeg = ffmpeg.clone();
+    }
+    if let Some(ffprobe) = &opts.ffprobe {
+        tools.ffprobe = ffprobe.clone();
+    }
+    tools.check()?;
+    Ok(tools)
+}
+
+// 输出路径：优先使用指定路径，再按同名文件处理方式调整，None 表示跳过
+fn resolve_output(opts: &CliOptions, default: impl FnOnce() -> PathBuf) -> Option<PathBuf> {
+    let output = opts.output.clone().unwrap_or_else(default);
+    naming::resolve_collision(output, opts.collision)
+}
+
+// 以 windows 子系统构建时进程没有控制台，命令行模式下附加到启动它的控制台以便输出
+#[cfg(target_os = "windows")]
+pub fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // 从资源管理器启动时没有父控制台，调用失败可以忽略
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn attach_console() {}
+
+// 命令行模式入口，返回进程退出码
+pub fn run(args: &[OsString]) -> i32 {
+    let (command, rest) = match args.first().and_then(|a| a.to_str()) {
+        Some(command @ ("concat" | "extract" | "remux" | "transcode")) => (command, &args[1..]),
+        _ => ("merge", args),
+    };
+
+    let opts = match parse(rest) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+
+    if opts.help {
+        println!("{}", USAGE);
+        return 0;
+    }
+
+    let tools = match setup_tools(&opts) {
+        Ok(tools) => tools,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 1;
+        }
+    };
+
+    let code = match command {
+        "concat" => run_concat(&tools, opts),
+        "extract" => run_extract(&tools, opts),
+        "remux" => run_remux(&tools, opts),
+        "transcode" => run_transcode(&tools, opts),
+        _ => run_merge(&tools, opts),
+    };
+
+    // 失败时输出最后一次失败调用的完整记录
+    if code != 0 {
+        if let Some(entry) = tools.log.entries().iter().rev().find(|e| !e.is_success()) {
+            eprintln!("\n{}", entry.to_text());
+        }
+    }
+    code
+}
+
+fn run_concat(tools: &Tools, opts: CliOptions) -> i32 {
+    let Some(first) = opts.inputs.first() else {
+        eprintln!("未指定片段\n\n{}", USAGE);
+        return 2;
+    };
+
+    let ext = first.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    let Some(output) = resolve_output(&opts, || naming::derived_path(first, "concat", ext)) else {
+        println!("输出文件已存在，已跳过");
+        return 0;
+    };
+
+    let overwrite = opts.collision == Collision::Overwrite;
+    match concat::concat(tools, &opts.inputs, &output, overwrite) {
+        Ok(result) => {
+            if let Some(warning) = &result.warning {
+                eprintln!("{}", warning);
+            }
+            println!(
+                "拼接成功（{}）！输出文件：{}",
+                result.method.describe(),
+                output.display()
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn run_extract(tools: &Tools, opts: CliOptions) -> i32 {
+    let Some(input) = opts.inputs.first() else {
+        eprintln!("未指定输入文件\n\n{}", USAGE);
+        return 2;
+    };
+    let Some(info) = probe::probe(tools, input) else {
+        eprintln!("无法探测 {}", input.display());
+        return 1;
+    };
+
+    if opts.list {
+        for stream in &info.streams {
+            println!("{}", stream.describe());
+        }
+        return 0;
+    }
+
+    let streams: Vec<StreamInfo> = info
+        .streams
+        .into_iter()
+        .filter(|s| {
+            opts.streams
+                .as_ref()
+                .is_none_or(|sel| sel.contains(&s.index))
+        })
+        .collect();
+    if streams.is_empty() {
+        eprintln!("没有匹配的流");
+        return 1;
+    }
+
+    match extract::extract(tools, input, &streams, opts.collision) {
+        Ok(outputs) => {
+            for output in outputs {
+                println!("{}", output.display());
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+fn run_remux(tools: &Tools, opts: CliOptions) -> i32 {
+    let Some(container) = opts.container else {
+        eprintln!("必须用 --container 指定目标格式\n\n{}", USAGE);
+        return 2;
+    };
+    if opts.inputs.is_empty() {
+        eprintln!("未指定输入文件\n\n{}", USAGE);
+        return 2;
+    }
+    if opts.output.is_some() && opts.inputs.len() > 1 {
+        eprintln!("多个输入时不能指定 -o");
+        return 2;
+    }
+
+    let mut failed = false;
+    for input in &opts.inputs {
+        let Some(output) = resolve_output(&opts, || remux::default_output(input, container)) else {
+            println!("{}: 输出文件已存在，已跳过", input.display());
+            continue;
+        };
+        let args = RemuxArgs::new(input, output, container)
+            .faststart(opts.faststart)
+            .strip_data(!opts.keep_data)
+            .strip_subtitles(opts.no_subtitles)
+            .overwrite(opts.collision == Collision::Overwrite)
+            .streams(probe::probe(tools, input).map(|info| info.streams));
+        for warning in args.warnings() {
+            eprintln!("{}: {}", input.display(), warning);
+        }
+        match remux::remux(tools, &args) {
+            Ok(output) => println!("{} → {}", input.display(), output.display()),
+            Err(e) => {
+                eprintln!("{}: {}", input.display(), e);
+                failed = true;
+            }
+        }
+    }
+
+    i32::from(failed)
+}
+
+// 界面保存的预设
+fn saved_profiles() -> Vec<Profile> {
+    encode::profiles_path()
+        .map(|path| encode::load_profiles(&path))
+        .unwrap_or_default()
+}
+
+// 与界面一致，有保存的预设时使用保存的预设，否则使用内置预设
+fn available_profiles(saved: Vec<Profile>) -> Vec<Profile> {
+    if saved.is_empty() {
+        Profile::builtin()
+    } else {
+        saved
+    }
+}
+
+// 预设名称可写全称，也可写内置预设的简称；省略时使用第一个预设
+fn select_profile(profiles: &[Profile], name: Option<&str>) -> Option<Profile> {
+    let Some(name) = name else {
+        return profiles.first().cloned();
+    };
+    let index = match name.to_lowercase().as_str() {
+        "h264" => Some(0),
+        "h265" | "hevc" => Some(1),
+        "av1" => Some(2),
+        "720p" => Some(3),
+        _ => None,
+    };
+    encode::find_profile(profiles, name)
+        .cloned()
+        .or_else(|| index.and_then(|i| Profile::builtin().into_iter().nth(i)))
+}
+
+fn run_transcode(tools: &Tools, opts: CliOptions) -> i32 {
+    let profiles = available_profiles(saved_profiles());
+    let Some(mut profile) = select_profile(&profiles, opts.profile.as_deref()) else {
+        eprintln!("未知的预设: {}", opts.profile.unwrap_or_default());
+        return 2;
+    };
+    if let Some(crf) = opts.crf {
+        if crf > profile.video.max_crf() {
+            eprintln!("CRF 超出范围 0-{}", profile.video.max_crf());
+            return 2;
+        }
+        profile.crf = crf;
+    }
+    if opts.inputs.is_empty() {
+        eprintln!("未指定输入文件\n\n{}", USAGE);
+        return 2;
+    }
+    if opts.output.is_some() && opts.inputs.len() > 1 {
+        eprintln!("多个输入时不能指定 -o");
+        return 2;
+    }
+
+    let container = opts.container.unwrap_or_default();
+    println!("预设 {}: {}", profile.name, profile.describe());
+    let mut failed = false;
+    for input in &opts.inputs {
+        let Some(output) = resolve_output(&opts, || encode::default_output(input, container))
+        else {
+            println!("{}: 输出文件已存在，已跳过", input.display());
+            continue;
+        };
+        let args = TranscodeArgs::new(input, output, container, profile.clone())
+            .overwrite(opts.collision == Collision::Overwrite);
+        println!("{}", tools.ffmpeg_command_line(&args.build()));
+        if opts.dry_run {
+            continue;
+        }
+        match encode::transcode(tools, &args) {
+            Ok(output) => println!("{} → {}", input.display(), output.display()),
+            Err(e) => {
+                eprintln!("{}: {}", input.display(), e);
+                failed = true;
+            }
+        }
+    }
+
+    i32::from(failed)
+}
+
+fn run_merge(tools: &Tools, opts: CliOptions) -> i32 {
+    if let Some(input) = opts.inputs.first() {
+        eprintln!("未知参数: {}\n\n{}", input.display(), USAGE);
+        return 2;
+    }
+
+    let (Some(video), Some(audio)) = (opts.video.clone(), opts.audio.clone()) else {
+        eprintln!("必须同时指定视频和音频文件\n\n{}", USAGE);
+        return 2;
+    };
+
+    let container = opts.container.unwrap_or_default();
+    let template = opts
+        .name_template
+        .as_deref()
+        .unwrap_or(naming::DEFAULT_TEMPLATE);
+    let Some(output) = resolve_output(&opts, || {
+        naming::output_path(tools, template, &video, Some(&audio), container)
+    }) else {
+        println!("输出文件已存在，已跳过");
+        return 0;
+    };
+    if let (Some(v), Some(a)) = (
+        probe::duration(tools, &video),
+        probe::duration(tools, &audio),
+    ) {
+        if let Some(diff) = probe::duration_mismatch(v, a, opts.audio_delay_ms) {
+            eprintln!("{}", probe::mismatch_warning(diff));
+        }
+    }
+
+    // 命令行指定的字段覆盖元数据文件中的值
+    let mut metadata = match &opts.metadata {
+        Some(path) => match metadata::read_file(path) {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        },
+        None => Metadata::default(),
+    };
+    for (field, value) in [
+        (&mut metadata.title, opts.title),
+        (&mut metadata.artist, opts.artist),
+        (&mut metadata.comment, opts.comment),
+    ] {
+        if let Some(value) = value {
+            *field = value;
+        }
+    }
+    let metadata_file = if metadata.is_empty() {
+        None
+    } else {
+        match MetadataFile::create(&metadata) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                eprintln!("{}", e);
+                return 1;
+            }
+        }
+    };
+
+    let merge_args = MergeArgs::new(video, audio, output)
+        .container(opts.container)
+        .audio_delay(opts.audio_delay_ms)
+        .shortest(opts.shortest)
+        .overwrite(opts.collision == Collision::Overwrite)
+        .metadata_file(metadata_file.as_ref().map(|f| f.path().to_path_buf()))
+        .cover(opts.cover);
+
+    let job = MergeJob {
+        args: merge_args,
+        audio: opts.audio_filters,
+        segments: opts.segments,
+        accurate_cut: opts.accurate,
+        delete_sources: opts.delete_sources,
+    };
+
+    match job::run(tools, &job) {
+        Ok(message) => {
+            println!("{}", message);
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn parses_paths_and_values() {
+        let opts = parse(&args(&[
+            "-v",
+            "v.mp4",
+            "-a",
+            "a.m4a",
+            "--audio-delay",
+            "-250",
+        ]))
+        .unwrap();
+        assert_eq!(opts.video, Some(PathBuf::from("v.mp4")));
+        assert_eq!(opts.audio, Some(PathBuf::from("a.m4a")));
+        assert_eq!(opts.audio_delay_ms, -250);
+        assert!(parse(&args(&["--bogus"])).is_err());
+        assert!(parse(&args(&["-v"])).is_err());
+    }
+
+    #[test]
+    fn selects_saved_profiles_before_builtin() {
+        let mut custom = Profile::builtin()[3].clone();
+        custom.name = "My 1080p".into();
+        custom.max_height = 1080;
+        let profiles = available_profiles(vec![custom.clone()]);
+
+        assert_eq!(select_profile(&profiles, None), Some(custom.clone()));
+        assert_eq!(select_profile(&profiles, Some("my 1080P")), Some(custom));
+        // 内置预设的简称仍然可用
+        assert_eq!(
+            select_profile(&profiles, Some("hevc")).map(|p| p.name),
+            Some("H.265 小体积".to_string())
+        );
+        assert_eq!(select_profile(&profiles, Some("missing")), None);
+
+        let builtin = available_profiles(Vec::new());
+        assert_eq!(builtin, Profile::builtin());
+        assert_eq!(
+            select_profile(&builtin, Some("AV1")),
+            Some(builtin[2].clone())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_non_utf8_paths() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let name = OsString::from_vec(b"clip\xff.mp4".to_vec());
+        let opts = parse(&[OsString::from("-v"), name.clone(), name.clone()]).unwrap();
+        assert_eq!(opts.video, Some(PathBuf::from(&name)));
+        assert_eq!(opts.inputs, [PathBuf::from(&name)]);
+
+        // 文本参数仍要求 UTF-8
+        let err = parse(&[OsString::from("--title"), name]).unwrap_err();
+        assert!(err.contains("不是有效的文本"), "{}", err);
+    }
+}
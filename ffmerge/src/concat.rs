@@ -0,0 +1,422 @@
+use crate::probe::{self, MediaInfo, StreamInfo};
+use crate::tools::Tools;
+use crate::trim::WorkDir;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// 拼接方式
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Method {
+    // concat demuxer，无损流复制
+    Demuxer,
+    // concat 滤镜，参数不一致时重新编码
+    Filter,
+}
+
+impl Method {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Method::Demuxer => "无损拼接",
+            Method::Filter => "重新编码",
+        }
+    }
+}
+
+// 拼接结果，warning 为需要提示用户的问题
+#[derive(Clone, Debug, PartialEq)]
+pub struct Concatenated {
+    pub method: Method,
+    pub warning: Option<String>,
+}
+
+// concat demuxer 列表中的相对路径以列表文件为基准，统一转为绝对路径并转义单引号
+pub fn demuxer_list(parts: &[PathBuf]) -> String {
+    parts
+        .iter()
+        .map(|p| {
+            let path = std::path::absolute(p).unwrap_or_else(|_| p.clone());
+            format!(
+                "file '{}'\n",
+                path.display().to_string().replace('\'', "'\\''")
+            )
+        })
+        .collect()
+}
+
+pub fn demuxer_args(list: &Path, output: &Path, overwrite: bool) -> Vec<OsString> {
+    vec![
+        if overwrite { "-y" } else { "-n" }.into(),
+        "-f".into(),
+        "concat".into(),
+        "-safe".into(),
+        "0".into(),
+        "-i".into(),
+        list.into(),
+        "-map".into(),
+        "0".into(),
+        "-c".into(),
+        "copy".into(),
+        output.into(),
+    ]
+}
+
+// 统一缩放到第一个片段的分辨率与帧率后用 concat 滤镜拼接；
+// 拼接音频时，没有音频的片段用与其等长的静音填充
+pub fn filter_args(
+    clips: &[PathBuf],
+    infos: &[MediaInfo],
+    with_audio: bool,
+    output: &Path,
+    overwrite: bool,
+) -> Vec<OsString> {
+    let first = &infos[0];
+    let (width, height) = first.resolution().unwrap_or((1920, 1080));
+    let fps = first
+        .first_stream("video")
+        .map(|s| s.frame_rate.as_str())
+        .filter(|r| !r.is_empty() && *r != "0/0")
+        .unwrap_or("30");
+
+    let mut args: Vec<OsString> = vec![if overwrite { "-y" } else { "-n" }.into()];
+    for clip in clips {
+        args.extend(["-i".into(), clip.into()]);
+    }
+
+    let mut filter = String::new();
+    let mut inputs = String::new();
+    for (i, info) in infos.iter().enumerate() {
+        filter.push_str(&format!(
+            "[{i}:v:0]scale={width}:{height}:force_original_aspect_ratio=decrease,\
+             pad={width}:{height}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p[v{i}];"
+        ));
+        inputs.push_str(&format!("[v{i}]"));
+        if with_audio {
+            if info.first_stream("audio").is_some() {
+                filter.push_str(&format!(
+                    "[{i}:a:0]aresample=48000,aformat=channel_layouts=stereo[a{i}];"
+                ));
+            } else {
+                filter.push_str(&format!(
+                    "anullsrc=channel_layout=stereo:sample_rate=48000,atrim=duration={:.3}[a{i}];",
+                    info.duration.unwrap_or_default()
+                ));
+            }
+            inputs.push_str(&format!("[a{i}]"));
+        }
+    }
+    filter.push_str(&format!(
+        "{inputs}concat=n={}:v=1:a={}[v]{}",
+        clips.len(),
+        u8::from(with_audio),
+        if with_audio { "[a]" } else { "" }
+    ));
+
+    args.extend([
+        "-filter_complex".into(),
+        filter.into(),
+        "-map".into(),
+        "[v]".into(),
+    ]);
+    if with_audio {
+        args.extend(["-map".into(), "[a]".into(), "-c:a".into(), "aac".into()]);
+    }
+    args.extend([
+        "-c:v".into(),
+        "libx264".into(),
+        "-crf".into(),
+        "18".into(),
+        "-preset".into(),
+        "medium".into(),
+        output.into(),
+    ]);
+    args
+}
+
+// 用于比较的流参数
+fn stream_signature(stream: &StreamInfo) -> String {
+    match stream.codec_type.as_str() {
+        "video" => format!(
+            "{} {}x{} {} {}",
+            stream.codec_name,
+            stream.width.unwrap_or_default(),
+            stream.height.unwrap_or_default(),
+            stream.pix_fmt,
+            stream.frame_rate
+        ),
+        "audio" => format!(
+            "{} {}Hz {}ch",
+            stream.codec_name,
+            stream.sample_rate.unwrap_or_default(),
+            stream.channels.unwrap_or_default()
+        ),
+        other => format!("{} {}", other, stream.codec_name),
+    }
+}
+
+fn signature(info: &MediaInfo) -> Vec<String> {
+    info.streams
+        .iter()
+        .filter(|s| s.codec_type == "video" || s.codec_type == "audio")
+        .map(stream_signature)
+        .collect()
+}
+
+// 检查所有片段的流参数是否与第一个一致，返回第一处差异
+pub fn check_compatible(clips: &[PathBuf], infos: &[MediaInfo]) -> Result<(), String> {
+    let Some(first) = infos.first() else {
+        return Ok(());
+    };
+    let expected = signature(first);
+
+    for (clip, info) in clips.iter().zip(infos).skip(1) {
+        let actual = signature(info);
+        if actual != expected {
+            return Err(format!(
+                "{} 的参数 [{}] 与第一个片段 [{}] 不同",
+                clip.file_name().unwrap_or_default().to_string_lossy(),
+                actual.join(", "),
+                expected.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+pub fn probe_all(tools: &Tools, clips: &[PathBuf]) -> Result<Vec<MediaInfo>, String> {
+    clips
+        .iter()
+        .map(|clip| probe::probe(tools, clip).ok_or_else(|| format!("无法探测 {}", clip.display())))
+        .collect()
+}
+
+// 是否拼接音频：只要有片段带音频就拼接，但无法获取无音频片段的时长时只能舍弃音频
+fn audio_plan(clips: &[PathBuf], infos: &[MediaInfo]) -> (bool, Option<String>) {
+    let silent = |info: &MediaInfo| info.first_stream("audio").is_none();
+    if infos.iter().all(silent) {
+        return (false, None);
+    }
+    match clips
+        .iter()
+        .zip(infos)
+        .find(|(_, info)| silent(info) && info.duration.is_none())
+    {
+        Some((clip, _)) => (
+            false,
+            Some(format!(
+                "⚠ 无法获取 {} 的时长，输出不含音频",
+                clip.file_name().unwrap_or_default().to_string_lossy()
+            )),
+        ),
+        None => (true, None),
+    }
+}
+
+// 按顺序拼接片段，参数一致时无损拼接，否则重新编码
+pub fn concat(
+    tools: &Tools,
+    clips: &[PathBuf],
+    output: &Path,
+    overwrite: bool,
+) -> Result<Concatenated, String> {
+    if clips.len() < 2 {
+        return Err("至少需要两个片段".to_string());
+    }
+
+    let infos = probe_all(tools, clips)?;
+    let work = WorkDir::create()?;
+    let mut warning = None;
+    let (method, args) = if check_compatible(clips, &infos).is_ok() {
+        let list = work.join("concat.txt");
+        std::fs::write(&list, demuxer_list(clips)).map_err(|e| format!("无法写入列表: {}", e))?;
+        (Method::Demuxer, demuxer_args(&list, output, overwrite))
+    } else {
+        let (with_audio, audio_warning) = audio_plan(clips, &infos);
+        warning = audio_warning;
+        (
+            Method::Filter,
+            filter_args(clips, &infos, with_audio, output, overwrite),
+        )
+    };
+
+    tools.execute_ffmpeg(&args, "拼接")?;
+    Ok(Concatenated { method, warning })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{self, Reply, StubRunner};
+
+    fn info(duration: Option<f64>, streams: &[(&str, &str)]) -> MediaInfo {
+        let mut info = probe::parse(&stub::probe_output(0.0, streams));
+        info.duration = duration;
+        info
+    }
+
+    fn filter(args: &[OsString]) -> String {
+        let i = args.iter().position(|a| a == "-filter_complex").unwrap();
+        args[i + 1].to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn pads_silent_clips_with_silence() {
+        let clips = [PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let infos = [
+            info(Some(5.0), &[("video", "h264"), ("audio", "aac")]),
+            info(Some(2.5), &[("video", "h264")]),
+        ];
+        assert_eq!(audio_plan(&clips, &infos), (true, None));
+
+        let args = filter_args(&clips, &infos, true, Path::new("out.mp4"), false);
+        let filter = filter(&args);
+        assert!(filter.contains("[0:a:0]aresample=48000"), "{}", filter);
+        assert!(
+            filter.contains(
+                "anullsrc=channel_layout=stereo:sample_rate=48000,atrim=duration=2.500[a1];"
+            ),
+            "{}",
+            filter
+        );
+        assert!(!filter.contains("[1:a:0]"), "{}", filter);
+        assert!(
+            filter.ends_with("[v0][a0][v1][a1]concat=n=2:v=1:a=1[v][a]"),
+            "{}",
+            filter
+        );
+        assert!(args.windows(2).any(|w| w == ["-map", "[a]"]));
+    }
+
+    #[test]
+    fn drops_audio_with_warning_when_silent_clip_has_no_duration() {
+        let clips = [PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let infos = [
+            info(Some(5.0), &[("video", "h264"), ("audio", "aac")]),
+            info(None, &[("video", "h264")]),
+        ];
+        let (with_audio, warning) = audio_plan(&clips, &infos);
+        assert!(!with_audio);
+        assert!(warning.unwrap().contains("b.mp4"));
+
+        let args = filter_args(&clips, &infos, false, Path::new("out.mp4"), false);
+        assert!(filter(&args).ends_with("concat=n=2:v=1:a=0[v]"));
+        assert!(!args.iter().any(|a| a == "[a]"));
+    }
+
+    #[test]
+    fn all_silent_clips_need_no_warning() {
+        let clips = [PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        let infos = [
+            info(None, &[("video", "h264")]),
+            info(None, &[("video", "h264")]),
+        ];
+        assert_eq!(audio_plan(&clips, &infos), (false, None));
+    }
+
+    #[test]
+    fn concat_mixed_audio_uses_filter() {
+        let runner = StubRunner::new(|program, args| {
+            if !program.ends_with("ffprobe") {
+                return Reply::default();
+            }
+            let clip = args.last().unwrap().to_string_lossy().to_string();
+            let streams: &[(&str, &str)] = if clip == "a.mp4" {
+                &[("video", "h264"), ("audio", "aac")]
+            } else {
+                &[("video", "h264")]
+            };
+            Reply::ok(&stub::probe_output(4.0, streams))
+        });
+        let clips = [PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+
+        let result = concat(&runner.tools(), &clips, Path::new("out.mp4"), false).unwrap();
+        assert_eq!(
+            result,
+            Concatenated {
+                method: Method::Filter,
+                warning: None
+            }
+        );
+        let (_, args) = runner.calls().pop().unwrap();
+        assert!(filter(&args).contains("atrim=duration=4.000[a1]"));
+    }
+
+    #[test]
+    fn quotes_demuxer_list_paths() {
+        let list = demuxer_list(&[
+            PathBuf::from("/clips/it's.mp4"),
+            PathBuf::from("/clips/b.mp4"),
+        ]);
+        assert_eq!(list, "file '/clips/it'\\''s.mp4'\nfile '/clips/b.mp4'\n");
+    }
+
+    #[test]
+    fn reports_first_incompatible_clip() {
+        let clips = [
+            PathBuf::from("a.mp4"),
+            PathBuf::from("b.mp4"),
+            PathBuf::from("c.mp4"),
+        ];
+        let same = info(Some(1.0), &[("video", "h264"), ("audio", "aac")]);
+        let infos = [same.clone(), same.clone(), same];
+        assert_eq!(check_compatible(&clips, &infos), Ok(()));
+
+        let mut infos = infos;
+        infos[2].streams[0].width = Some(1280);
+        let error = check_compatible(&clips, &infos).unwrap_err();
+        assert!(
+            error.starts_with("c.mp4 的参数 [h264 1280x1080"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn concat_compatible_clips_uses_demuxer() {
+        let runner = StubRunner::new(|program, _| {
+            if program.ends_with("ffprobe") {
+                Reply::ok(&stub::probe_output(
+                    4.0,
+                    &[("video", "h264"), ("audio", "aac")],
+                ))
+            } else {
+                Reply::default()
+            }
+        });
+        let clips = [PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+
+        let result = concat(&runner.tools(), &clips, Path::new("out.mp4"), true).unwrap();
+        assert_eq!(result.method, Method::Demuxer);
+        assert_eq!(result.warning, None);
+        assert_eq!(stub::calls_to(&runner, "ffprobe").len(), 2);
+        let calls = stub::calls_to(&runner, "ffmpeg");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0][..5], ["-y", "-f", "concat", "-safe", "0"]);
+        assert!(calls[0][6].ends_with("concat.txt"));
+        assert_eq!(calls[0][7..], ["-map", "0", "-c", "copy", "out.mp4"]);
+    }
+
+    #[test]
+    fn concat_needs_two_probed_clips() {
+        let runner = StubRunner::new(|_, _| Reply::fail(1, "No such file"));
+        let tools = runner.tools();
+
+        assert_eq!(
+            concat(
+                &tools,
+                &[PathBuf::from("a.mp4")],
+                Path::new("out.mp4"),
+                false
+            )
+            .unwrap_err(),
+            "至少需要两个片段"
+        );
+        assert!(runner.calls().is_empty());
+
+        let clips = [PathBuf::from("a.mp4"), PathBuf::from("b.mp4")];
+        assert_eq!(
+            concat(&tools, &clips, Path::new("out.mp4"), false).unwrap_err(),
+            "无法探测 a.mp4"
+        );
+        assert!(stub::calls_to(&runner, "ffmpeg").is_empty());
+    }
+}
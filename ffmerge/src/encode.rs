@@ -0,0 +1,522 @@
+use crate::ffmpeg::Container;
+use crate::naming;
+use crate::tools::Tools;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// 视频编码器，均为软件编码
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VideoCodec {
+    Copy,
+    X264,
+    X265,
+    SvtAv1,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 4] = [
+        VideoCodec::Copy,
+        VideoCodec::X264,
+        VideoCodec::X265,
+        VideoCodec::SvtAv1,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            VideoCodec::Copy => "copy",
+            VideoCodec::X264 => "libx264",
+            VideoCodec::X265 => "libx265",
+            VideoCodec::SvtAv1 => "libsvtav1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            VideoCodec::Copy => "不转码",
+            VideoCodec::X264 => "H.264 (x264)",
+            VideoCodec::X265 => "H.265 (x265)",
+            VideoCodec::SvtAv1 => "AV1 (SVT-AV1)",
+        }
+    }
+
+    // CRF 上限，数值越大体积越小
+    pub fn max_crf(self) -> u8 {
+        match self {
+            VideoCodec::SvtAv1 => 63,
+            _ => 51,
+        }
+    }
+
+    // 可选的速度档位，由快到慢
+    pub fn speeds(self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::Copy => &[],
+            VideoCodec::X264 | VideoCodec::X265 => &[
+                "ultrafast",
+                "superfast",
+                "veryfast",
+                "faster",
+                "fast",
+                "medium",
+                "slow",
+                "slower",
+                "veryslow",
+            ],
+            VideoCodec::SvtAv1 => &["12", "10", "8", "6", "4"],
+        }
+    }
+
+    pub fn default_speed(self) -> &'static str {
+        match self {
+            VideoCodec::Copy => "",
+            VideoCodec::X264 | VideoCodec::X265 => "medium",
+            VideoCodec::SvtAv1 => "8",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AudioCodec {
+    Copy,
+    Aac,
+    Opus,
+}
+
+impl AudioCodec {
+    pub const ALL: [AudioCodec; 3] = [AudioCodec::Copy, AudioCodec::Aac, AudioCodec::Opus];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AudioCodec::Copy => "copy",
+            AudioCodec::Aac => "aac",
+            AudioCodec::Opus => "libopus",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            AudioCodec::Copy => "不转码",
+            AudioCodec::Aac => "AAC",
+            AudioCodec::Opus => "Opus",
+        }
+    }
+}
+
+// 转码配置，可在界面中编辑并保存为预设
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub video: VideoCodec,
+    pub crf: u8,
+    pub speed: String,
+    pub audio: AudioCodec,
+    // 音频码率（kbps）
+    pub audio_bitrate: u32,
+    // 高度与帧率上限，0 表示不限制
+    pub max_height: u32,
+    pub max_fps: u32,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: "新预设".into(),
+            video: VideoCodec::X264,
+            crf: 23,
+            speed: "medium".into(),
+            audio: AudioCodec::Aac,
+            audio_bitrate: 160,
+            max_height: 0,
+            max_fps: 0,
+        }
+    }
+}
+
+impl Profile {
+    pub fn builtin() -> Vec<Profile> {
+        vec![
+            Profile {
+                name: "H.264 高质量".into(),
+                video: VideoCodec::X264,
+                crf: 20,
+                speed: "medium".into(),
+                audio: AudioCodec::Aac,
+                audio_bitrate: 192,
+                max_height: 0,
+                max_fps: 0,
+            },
+            Profile {
+                name: "H.265 小体积".into(),
+                video: VideoCodec::X265,
+                crf: 26,
+                speed: "medium".into(),
+                audio: AudioCodec::Aac,
+                audio_bitrate: 128,
+                max_height: 0,
+                max_fps: 0,
+            },
+            Profile {
+                name: "AV1".into(),
+                video: VideoCodec::SvtAv1,
+                crf: 32,
+                speed: "8".into(),
+                audio: AudioCodec::Opus,
+                audio_bitrate: 128,
+                max_height: 0,
+                max_fps: 0,
+            },
+            Profile {
+                name: "720p 分享".into(),
+                video: VideoCodec::X264,
+                crf: 23,
+                speed: "fast".into(),
+                audio: AudioCodec::Aac,
+                audio_bitrate: 128,
+                max_height: 720,
+                max_fps: 30,
+            },
+        ]
+    }
+
+    // 切换编码器时把 CRF 与速度档位调整到新编码器的有效范围
+    pub fn set_video(&mut self, video: VideoCodec) {
+        self.video = video;
+        self.crf = self.crf.min(video.max_crf());
+        if !video.speeds().contains(&self.speed.as_str()) {
+            self.speed = video.default_speed().to_string();
+        }
+    }
+
+    // 编码相关的输出参数
+    pub fn args(&self, container: Container) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec!["-c:v".into(), self.video.name().into()];
+        if self.video != VideoCodec::Copy {
+            args.extend([
+                "-crf".into(),
+                self.crf.to_string().into(),
+                "-preset".into(),
+                self.speed.clone().into(),
+                "-pix_fmt".into(),
+                "yuv420p".into(),
+            ]);
+            if self.max_height > 0 {
+                // 只缩小不放大，宽度按比例取偶数
+                args.extend([
+                    "-vf".into(),
+                    format!("scale=-2:'min(ih,{})'", self.max_height).into(),
+                ]);
+            }
+            if self.max_fps > 0 {
+                // 只降低超过上限的帧率，需要 ffmpeg 4.4 及以上（见 tools::MIN_VERSION）
+                args.extend(["-fpsmax".into(), self.max_fps.to_string().into()]);
+            }
+            if self.video == VideoCodec::X265 && container != Container::Mkv {
+                // Apple 设备只识别 hvc1 标签
+                args.extend(["-tag:v".into(), "hvc1".into()]);
+            }
+        }
+
+        args.extend(["-c:a".into(), self.audio.name().into()]);
+        if self.audio != AudioCodec::Copy {
+            args.extend(["-b:a".into(), format!("{}k", self.audio_bitrate).into()]);
+        }
+        args
+    }
+
+    pub fn describe(&self) -> String {
+        let mut text = match self.video {
+            VideoCodec::Copy => self.video.describe().to_string(),
+            video => format!("{} CRF {} {}", video.describe(), self.crf, self.speed),
+        };
+        if self.max_height > 0 {
+            text.push_str(&format!(" ≤{}p", self.max_height));
+        }
+        if self.max_fps > 0 {
+            text.push_str(&format!(" ≤{}fps", self.max_fps));
+        }
+        text.push_str(&format!(" / {}", self.audio.describe()));
+        if self.audio != AudioCodec::Copy {
+            text.push_str(&format!(" {}k", self.audio_bitrate));
+        }
+        text
+    }
+}
+
+// 旧版本界面把预设保存在 eframe 持久化存储中的键，仅用于迁移
+pub const PROFILES_KEY: &str = "encode_profiles";
+
+// 保存的预设文件，与 eframe 的持久化文件位于同一目录，界面与命令行共用
+pub fn profiles_path() -> Option<PathBuf> {
+    eframe::storage_dir(crate::APP_NAME).map(|dir| dir.join("profiles.txt"))
+}
+
+// 读取保存的预设，文件不存在或无法读取时返回空列表
+pub fn load_profiles(path: &Path) -> Vec<Profile> {
+    std::fs::read_to_string(path)
+        .map(|text| parse_profiles(&text))
+        .unwrap_or_default()
+}
+
+pub fn save_profiles(path: &Path, profiles: &[Profile]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("无法创建预设目录: {}", e))?;
+    }
+    std::fs::write(path, profiles_to_text(profiles)).map_err(|e| format!("无法保存预设: {}", e))
+}
+
+// 预设的文本格式，每个预设一段 [PROFILE]，其后为 key=value
+pub fn profiles_to_text(profiles: &[Profile]) -> String {
+    profiles
+        .iter()
+        .map(|p| {
+            format!(
+                "[PROFILE]\nname={}\nvideo={}\ncrf={}\nspeed={}\naudio={}\naudio_bitrate={}\nmax_height={}\nmax_fps={}\n",
+                p.name,
+                p.video.name(),
+                p.crf,
+                p.speed,
+                p.audio.name(),
+                p.audio_bitrate,
+                p.max_height,
+                p.max_fps
+            )
+        })
+        .collect()
+}
+
+// 解析预设文本，无法识别的值保留默认
+pub fn parse_profiles(text: &str) -> Vec<Profile> {
+    let mut profiles: Vec<Profile> = Vec::new();
+    for line in text.lines() {
+        if line.trim() == "[PROFILE]" {
+            profiles.push(Profile::default());
+            continue;
+        }
+        let (Some(profile), Some((key, value))) = (profiles.last_mut(), line.split_once('='))
+        else {
+            continue;
+        };
+        match key {
+            "name" => profile.name = value.to_string(),
+            "video" => profile.video = VideoCodec::from_name(value).unwrap_or(profile.video),
+            "crf" => profile.crf = value.parse().unwrap_or(profile.crf),
+            "speed" => profile.speed = value.to_string(),
+            "audio" => profile.audio = AudioCodec::from_name(value).unwrap_or(profile.audio),
+            "audio_bitrate" => {
+                profile.audio_bitrate = value.parse().unwrap_or(profile.audio_bitrate)
+            }
+            "max_height" => profile.max_height = value.parse().unwrap_or(0),
+            "max_fps" => profile.max_fps = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+    profiles
+}
+
+pub fn find_profile<'a>(profiles: &'a [Profile], name: &str) -> Option<&'a Profile> {
+    profiles.iter().find(|p| p.name.eq_ignore_ascii_case(name))
+}
+
+// 转码参数构建器，只保留第一条视频流与全部音频流
+#[derive(Clone, Debug)]
+pub struct TranscodeArgs {
+    input: PathBuf,
+    output: PathBuf,
+    container: Container,
+    profile: Profile,
+    overwrite: bool,
+}
+
+impl TranscodeArgs {
+    pub fn new(
+        input: impl Into<PathBuf>,
+        output: impl Into<PathBuf>,
+        container: Container,
+        profile: Profile,
+    ) -> Self {
+        Self {
+            input: input.into(),
+            output: output.into(),
+            container,
+            profile,
+            overwrite: false,
+        }
+    }
+
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    pub fn build(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![
+            if self.overwrite { "-y" } else { "-n" }.into(),
+            "-i".into(),
+            self.input.clone().into(),
+            "-map".into(),
+            "0:v:0?".into(),
+            "-map".into(),
+            "0:a?".into(),
+        ];
+        args.extend(self.profile.args(self.container));
+        if self.container != Container::Mkv {
+            args.extend(["-movflags".into(), "+faststart".into()]);
+        }
+        args.extend([
+            "-f".into(),
+            self.container.muxer().into(),
+            self.output.clone().into(),
+        ]);
+        args
+    }
+}
+
+pub fn default_output(input: &Path, container: Container) -> PathBuf {
+    naming::derived_path(input, "encoded", container.extension())
+}
+
+pub fn transcode(tools: &Tools, args: &TranscodeArgs) -> Result<PathBuf, String> {
+    tools.execute_ffmpeg(&args.build(), "转码")?;
+    Ok(args.output().to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{self, strings, Reply, StubRunner};
+    use crate::trim::WorkDir;
+
+    #[test]
+    fn builds_transcode_args() {
+        let profile = Profile::builtin()[0].clone();
+        let args = TranscodeArgs::new("in.mkv", "out.mp4", Container::Mp4, profile).build();
+        assert_eq!(
+            strings(&args),
+            [
+                "-n",
+                "-i",
+                "in.mkv",
+                "-map",
+                "0:v:0?",
+                "-map",
+                "0:a?",
+                "-c:v",
+                "libx264",
+                "-crf",
+                "20",
+                "-preset",
+                "medium",
+                "-pix_fmt",
+                "yuv420p",
+                "-c:a",
+                "aac",
+                "-b:a",
+                "192k",
+                "-movflags",
+                "+faststart",
+                "-f",
+                "mp4",
+                "out.mp4"
+            ]
+        );
+    }
+
+    #[test]
+    fn limits_height_and_frame_rate() {
+        let mut profile = Profile::builtin()[3].clone();
+        profile.set_video(VideoCodec::X265);
+        let args = strings(&profile.args(Container::Mov));
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["-vf", "scale=-2:'min(ih,720)'"]));
+        assert!(args.windows(2).any(|w| w == ["-fpsmax", "30"]));
+        assert!(args.windows(2).any(|w| w == ["-tag:v", "hvc1"]));
+
+        // mkv 不需要 hvc1 标签
+        let args = strings(&profile.args(Container::Mkv));
+        assert!(!args.contains(&"hvc1".to_string()));
+    }
+
+    #[test]
+    fn copy_profile_has_no_encoder_options() {
+        let profile = Profile {
+            video: VideoCodec::Copy,
+            audio: AudioCodec::Copy,
+            max_height: 720,
+            max_fps: 30,
+            ..Default::default()
+        };
+        assert_eq!(
+            strings(&profile.args(Container::Mp4)),
+            ["-c:v", "copy", "-c:a", "copy"]
+        );
+    }
+
+    #[test]
+    fn switching_codec_keeps_settings_valid() {
+        let mut profile = Profile {
+            crf: 60,
+            speed: "veryslow".into(),
+            ..Default::default()
+        };
+        profile.set_video(VideoCodec::SvtAv1);
+        assert_eq!((profile.crf, profile.speed.as_str()), (60, "8"));
+        profile.set_video(VideoCodec::X264);
+        assert_eq!((profile.crf, profile.speed.as_str()), (51, "medium"));
+    }
+
+    #[test]
+    fn profiles_round_trip_through_text() {
+        let profiles = Profile::builtin();
+        assert_eq!(parse_profiles(&profiles_to_text(&profiles)), profiles);
+        assert!(parse_profiles("name=orphan\n").is_empty());
+        assert_eq!(
+            find_profile(&profiles, "av1").map(|p| p.video),
+            Some(VideoCodec::SvtAv1)
+        );
+    }
+
+    #[test]
+    fn profiles_round_trip_through_file() {
+        let dir = WorkDir::create().unwrap();
+        let path = dir.join("settings").join("profiles.txt");
+        assert!(load_profiles(&path).is_empty());
+
+        let mut profiles = Profile::builtin();
+        profiles[0].name = "自定义".to_string();
+        profiles[0].crf = 18;
+        save_profiles(&path, &profiles).unwrap();
+        assert_eq!(load_profiles(&path), profiles);
+    }
+
+    #[test]
+    fn transcode_runs_built_args() {
+        let runner = StubRunner::new(|_, _| Reply::default());
+        let args = TranscodeArgs::new(
+            "in.mkv",
+            "out.mkv",
+            Container::Mkv,
+            Profile::builtin()[2].clone(),
+        )
+        .overwrite(true);
+
+        let output = transcode(&runner.tools(), &args).unwrap();
+        assert_eq!(output, PathBuf::from("out.mkv"));
+        let calls = stub::calls_to(&runner, "ffmpeg");
+        assert_eq!(calls, [strings(&args.build())]);
+        assert_eq!(calls[0][0], "-y");
+        assert!(!calls[0].contains(&"-movflags".to_string()));
+    }
+}
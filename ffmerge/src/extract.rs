@@ -0,0 +1,193 @@
+use crate::naming::{self, Collision};
+use crate::probe::StreamInfo;
+use crate::tools::Tools;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// 根据编码选择适合单独存放该流的扩展名
+fn extension_for(stream: &StreamInfo) -> &'static str {
+    match (stream.codec_type.as_str(), stream.codec_name.as_str()) {
+        ("video", "h264" | "hevc" | "av1" | "mpeg4") => "mp4",
+        ("video", "vp8" | "vp9") => "webm",
+        ("video", _) => "mkv",
+        ("audio", "aac" | "alac") => "m4a",
+        ("audio", "mp3") => "mp3",
+        ("audio", "opus") => "opus",
+        ("audio", "vorbis") => "ogg",
+        ("audio", "flac") => "flac",
+        ("audio", "ac3") => "ac3",
+        ("audio", "eac3") => "eac3",
+        ("audio", "dts") => "dts",
+        ("audio", codec) if codec.starts_with("pcm_") => "wav",
+        ("audio", _) => "mka",
+        ("subtitle", "subrip" | "mov_text") => "srt",
+        ("subtitle", "ass" | "ssa") => "ass",
+        ("subtitle", "webvtt") => "vtt",
+        ("subtitle", "hdmv_pgs_subtitle") => "sup",
+        ("subtitle", _) => "mks",
+        _ => "mkv",
+    }
+}
+
+// 输出文件名形如 movie.a1.m4a
+pub fn output_for(input: &Path, stream: &StreamInfo) -> PathBuf {
+    let prefix = match stream.codec_type.as_str() {
+        "video" => "v",
+        "audio" => "a",
+        "subtitle" => "s",
+        _ => "d",
+    };
+    naming::derived_path(
+        input,
+        &format!("{}{}", prefix, stream.index),
+        extension_for(stream),
+    )
+}
+
+// mov_text 无法直接复制到 srt，需要转换为文本字幕
+fn codec_for(stream: &StreamInfo) -> &'static str {
+    match stream.codec_name.as_str() {
+        "mov_text" => "srt",
+        _ => "copy",
+    }
+}
+
+// 一次调用输出所有选中的流，返回实际写入的文件
+pub fn extract(
+    tools: &Tools,
+    input: &Path,
+    streams: &[StreamInfo],
+    collision: Collision,
+) -> Result<Vec<PathBuf>, String> {
+    let mut args: Vec<OsString> = vec![
+        if collision == Collision::Overwrite {
+            "-y"
+        } else {
+            "-n"
+        }
+        .into(),
+        "-i".into(),
+        input.into(),
+    ];
+    let mut outputs = Vec::new();
+
+    for stream in streams {
+        let Some(output) = naming::resolve_collision(output_for(input, stream), collision) else {
+            continue;
+        };
+        args.extend([
+            "-map".into(),
+            format!("0:{}", stream.index).into(),
+            "-c".into(),
+            codec_for(stream).into(),
+            output.clone().into(),
+        ]);
+        outputs.push(output);
+    }
+
+    if outputs.is_empty() {
+        return Ok(outputs);
+    }
+
+    tools.execute_ffmpeg(&args, "提取")?;
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe;
+    use crate::stub::{self, Reply, StubRunner};
+    use crate::trim::WorkDir;
+
+    fn streams() -> Vec<StreamInfo> {
+        let text = stub::probe_output(
+            10.0,
+            &[
+                ("video", "hevc"),
+                ("audio", "aac"),
+                ("audio", "pcm_s16le"),
+                ("subtitle", "mov_text"),
+                ("data", "bin_data"),
+            ],
+        );
+        probe::parse(&text).streams
+    }
+
+    #[test]
+    fn names_outputs_by_stream() {
+        let names: Vec<PathBuf> = streams()
+            .iter()
+            .map(|s| output_for(Path::new("dir/movie.mkv"), s))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "dir/movie.v0.mp4",
+                "dir/movie.a1.m4a",
+                "dir/movie.a2.wav",
+                "dir/movie.s3.srt",
+                "dir/movie.d4.mkv"
+            ]
+            .map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn extracts_all_streams_in_one_call() {
+        let dir = WorkDir::create().unwrap();
+        let input = dir.join("movie.mp4");
+        let runner = StubRunner::new(|_, _| Reply::default());
+
+        let outputs = extract(
+            &runner.tools(),
+            &input,
+            &streams()[1..4],
+            Collision::Overwrite,
+        )
+        .unwrap();
+        assert_eq!(outputs.len(), 3);
+
+        let calls = stub::calls_to(&runner, "ffmpeg");
+        assert_eq!(calls.len(), 1);
+        let args = &calls[0];
+        assert_eq!(args[..2], ["-y", "-i"]);
+        assert_eq!(
+            args[3..],
+            [
+                "-map".to_string(),
+                "0:1".into(),
+                "-c".into(),
+                "copy".into(),
+                outputs[0].display().to_string(),
+                "-map".into(),
+                "0:2".into(),
+                "-c".into(),
+                "copy".into(),
+                outputs[1].display().to_string(),
+                "-map".into(),
+                "0:3".into(),
+                "-c".into(),
+                "srt".into(),
+                outputs[2].display().to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_existing_outputs() {
+        let dir = WorkDir::create().unwrap();
+        let input = dir.join("movie.mp4");
+        let streams = streams();
+        std::fs::write(output_for(&input, &streams[1]), "old").unwrap();
+        let runner = StubRunner::new(|_, _| Reply::default());
+
+        let outputs = extract(&runner.tools(), &input, &streams[1..2], Collision::Skip).unwrap();
+        assert!(outputs.is_empty());
+        assert!(runner.calls().is_empty());
+
+        let outputs = extract(&runner.tools(), &input, &streams[1..3], Collision::Skip).unwrap();
+        assert_eq!(outputs, [output_for(&input, &streams[2])]);
+        assert_eq!(stub::calls_to(&runner, "ffmpeg")[0][0], "-n");
+    }
+}
@@ -0,0 +1,314 @@
+use crate::tools::Tools;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// 输出容器格式
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Container {
+    #[default]
+    Mp4,
+    Mkv,
+    Mov,
+}
+
+impl Container {
+    pub const ALL: [Container; 3] = [Container::Mp4, Container::Mkv, Container::Mov];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "mp4" => Some(Container::Mp4),
+            "mkv" | "matroska" => Some(Container::Mkv),
+            "mov" => Some(Container::Mov),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mkv => "mkv",
+            Container::Mov => "mov",
+        }
+    }
+
+    // 传给 ffmpeg `-f` 的 muxer 名称
+    pub fn muxer(self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mkv => "matroska",
+            Container::Mov => "mov",
+        }
+    }
+}
+
+// 视频/音频合并的 ffmpeg 参数构建器
+#[derive(Clone, Debug)]
+pub struct MergeArgs {
+    video: PathBuf,
+    audio: PathBuf,
+    output: PathBuf,
+    container: Option<Container>,
+    audio_delay_ms: i64,
+    shortest: bool,
+    overwrite: bool,
+    metadata_file: Option<PathBuf>,
+    cover: Option<PathBuf>,
+    audio_filter: Option<String>,
+}
+
+impl MergeArgs {
+    pub fn new(
+        video: impl Into<PathBuf>,
+        audio: impl Into<PathBuf>,
+        output: impl Into<PathBuf>,
+    ) -> Self {
+        Self {
+            video: video.into(),
+            audio: audio.into(),
+            output: output.into(),
+            container: None,
+            audio_delay_ms: 0,
+            shortest: false,
+            overwrite: false,
+            metadata_file: None,
+            cover: None,
+            audio_filter: None,
+        }
+    }
+
+    // 强制指定输出容器，否则由 ffmpeg 根据扩展名推断
+    pub fn container(mut self, container: Option<Container>) -> Self {
+        self.container = container;
+        self
+    }
+
+    // 音频延迟（毫秒），负数表示音频提前
+    pub fn audio_delay(mut self, ms: i64) -> Self {
+        self.audio_delay_ms = ms;
+        self
+    }
+
+    // 以最短的流为准截断输出
+    pub fn shortest(mut self, shortest: bool) -> Self {
+        self.shortest = shortest;
+        self
+    }
+
+    // 覆盖已存在的输出文件
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    // 从 ffmetadata 文件写入全局元数据与章节
+    pub fn metadata_file(mut self, path: Option<PathBuf>) -> Self {
+        self.metadata_file = path;
+        self
+    }
+
+    // 作为封面附加的图片
+    pub fn cover(mut self, path: Option<PathBuf>) -> Self {
+        self.cover = path;
+        self
+    }
+
+    // 音频滤镜链，设置后音频重新编码为 AAC，视频仍为流复制
+    pub fn audio_filter(mut self, filter: Option<String>) -> Self {
+        self.audio_filter = filter;
+        self
+    }
+
+    // 替换输出路径，用于先输出到临时文件再做后续处理
+    pub fn with_output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = output.into();
+        self
+    }
+
+    pub fn video(&self) -> &Path {
+        &self.video
+    }
+
+    pub fn audio(&self) -> &Path {
+        &self.audio
+    }
+
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    pub fn audio_delay_ms(&self) -> i64 {
+        self.audio_delay_ms
+    }
+
+    pub fn is_shortest(&self) -> bool {
+        self.shortest
+    }
+
+    pub fn is_overwrite(&self) -> bool {
+        self.overwrite
+    }
+
+    pub fn build(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![if self.overwrite { "-y" } else { "-n" }.into()];
+        args.extend(["-i".into(), self.video.clone().into()]);
+        if self.audio_delay_ms != 0 {
+            // -itsoffset 作用于紧随其后的输入
+            args.push("-itsoffset".into());
+            args.push(format_seconds(self.audio_delay_ms).into());
+        }
+        args.extend(["-i".into(), self.audio.clone().into()]);
+
+        // 额外输入依次排在视频、音频之后，输出选项放在所有输入之后
+        let metadata_input = self.metadata_file.as_ref().map(|metadata| {
+            args.extend([
+                "-f".into(),
+                "ffmetadata".into(),
+                "-i".into(),
+                metadata.clone().into(),
+            ]);
+            2
+        });
+        let cover_input = self.cover.as_ref().map(|cover| {
+            args.extend(["-i".into(), cover.clone().into()]);
+            2 + usize::from(metadata_input.is_some())
+        });
+
+        if let Some(input) = metadata_input {
+            args.extend([
+                "-map_metadata".into(),
+                input.to_string().into(),
+                "-map_chapters".into(),
+                input.to_string().into(),
+            ]);
+        }
+        // 始终显式映射，避免视频文件自带的音轨被 ffmpeg 自动选中
+        args.extend(["-map".into(), "0:v:0".into(), "-map".into(), "1:a:0".into()]);
+        if let Some(input) = cover_input {
+            args.extend([
+                "-map".into(),
+                format!("{}:v:0", input).into(),
+                "-disposition:v:1".into(),
+                "attached_pic".into(),
+            ]);
+        }
+        args.extend(["-c".into(), "copy".into()]);
+        if let Some(filter) = &self.audio_filter {
+            args.extend([
+                "-af".into(),
+                filter.into(),
+                "-c:a".into(),
+                "aac".into(),
+                "-b:a".into(),
+                "192k".into(),
+            ]);
+        }
+        if self.shortest {
+            args.push("-shortest".into());
+        }
+        if let Some(container) = self.container {
+            args.push("-f".into());
+            args.push(container.muxer().into());
+        }
+        args.push(self.output.clone().into());
+        args
+    }
+}
+
+fn format_seconds(ms: i64) -> String {
+    let sign = if ms < 0 { "-" } else { "" };
+    let ms = ms.unsigned_abs();
+    format!("{}{}.{:03}", sign, ms / 1000, ms % 1000)
+}
+
+// 执行合并，成功时返回输出文件路径，失败时返回错误描述
+pub fn merge(tools: &Tools, args: &MergeArgs) -> Result<PathBuf, String> {
+    tools.execute_ffmpeg(&args.build(), "转换")?;
+
+    Ok(args.output().to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{strings, Reply, StubRunner};
+
+    #[test]
+    fn builds_plain_merge() {
+        let args = MergeArgs::new("v.mp4", "a.m4a", "out.mp4").build();
+        assert_eq!(
+            strings(&args),
+            [
+                "-n", "-i", "v.mp4", "-i", "a.m4a", "-map", "0:v:0", "-map", "1:a:0", "-c", "copy",
+                "out.mp4"
+            ]
+        );
+    }
+
+    #[test]
+    fn builds_merge_with_all_options() {
+        let args = MergeArgs::new("v.mp4", "a.m4a", "out.mkv")
+            .container(Some(Container::Mkv))
+            .audio_delay(-1500)
+            .shortest(true)
+            .overwrite(true)
+            .metadata_file(Some("meta.txt".into()))
+            .cover(Some("cover.jpg".into()))
+            .audio_filter(Some("volume=3dB".into()))
+            .build();
+        assert_eq!(
+            strings(&args),
+            [
+                "-y",
+                "-i",
+                "v.mp4",
+                "-itsoffset",
+                "-1.500",
+                "-i",
+                "a.m4a",
+                "-f",
+                "ffmetadata",
+                "-i",
+                "meta.txt",
+                "-i",
+                "cover.jpg",
+                "-map_metadata",
+                "2",
+                "-map_chapters",
+                "2",
+                "-map",
+                "0:v:0",
+                "-map",
+                "1:a:0",
+                "-map",
+                "3:v:0",
+                "-disposition:v:1",
+                "attached_pic",
+                "-c",
+                "copy",
+                "-af",
+                "volume=3dB",
+                "-c:a",
+                "aac",
+                "-b:a",
+                "192k",
+                "-shortest",
+                "-f",
+                "matroska",
+                "out.mkv"
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_runs_ffmpeg_with_built_args() {
+        let runner = StubRunner::new(|_, _| Reply::default());
+        let args = MergeArgs::new("v.mp4", "a.m4a", "out.mp4");
+
+        let output = merge(&runner.tools(), &args).unwrap();
+        assert_eq!(output, PathBuf::from("out.mp4"));
+        let calls = runner.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].0, PathBuf::from("ffmpeg"));
+        assert_eq!(calls[0].1, args.build());
+    }
+}
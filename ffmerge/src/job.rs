@@ -0,0 +1,200 @@
+use crate::audio::{self, AudioFilters};
+use crate::cleanup;
+use crate::ffmpeg::{self, MergeArgs};
+use crate::tools::Tools;
+use crate::trim::{self, Segment, WorkDir};
+
+// 一次完整的合并任务：可选的音频处理、合并、可选的片段剪切、可选的源文件清理
+pub struct MergeJob {
+    pub args: MergeArgs,
+    pub audio: AudioFilters,
+    pub segments: Vec<Segment>,
+    pub accurate_cut: bool,
+    pub delete_sources: bool,
+}
+
+// 执行任务，成功和失败都返回可直接显示的描述
+pub fn run(tools: &Tools, job: &MergeJob) -> Result<String, String> {
+    // 响度标准化需要先测量一遍音频
+    let filter = audio::prepare(tools, job.args.audio(), &job.audio)?;
+    let args = job.args.clone().audio_filter(filter);
+    if !job.segments.is_empty() {
+        return run_with_segments(tools, job, &args);
+    }
+
+    let output = ffmpeg::merge(tools, &args)?;
+    let message = format!("转换成功！输出文件：{}", output.display());
+    finish(tools, job, &args, message)
+}
+
+// 按需清理源文件；清理失败时整个任务按失败返回，输出文件仍保留
+fn finish(
+    tools: &Tools,
+    job: &MergeJob,
+    args: &MergeArgs,
+    message: String,
+) -> Result<String, String> {
+    if !job.delete_sources {
+        return Ok(message);
+    }
+    match cleanup::remove_sources(tools, args) {
+        Ok(status) => Ok(format!("{}\n{}", message, status)),
+        Err(e) => Err(format!("{}\n{}", message, e)),
+    }
+}
+
+// 先合并到临时文件，再剪切到最终输出
+fn run_with_segments(tools: &Tools, job: &MergeJob, args: &MergeArgs) -> Result<String, String> {
+    let work = WorkDir::create()?;
+    let ext = args
+        .output()
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_else(|| "mp4".into());
+    let merged_args = args
+        .clone()
+        .with_output(work.join(&format!("merged.{}", ext)))
+        .overwrite(true);
+    let merged = ffmpeg::merge(tools, &merged_args)?;
+
+    let kept = trim::cut(
+        tools,
+        &merged,
+        args.output(),
+        &job.segments,
+        job.accurate_cut,
+        args.is_overwrite(),
+    )?;
+    let kept: Vec<String> = kept.iter().map(Segment::describe).collect();
+    let message = format!(
+        "转换成功！输出文件：{}\n保留片段：{}",
+        args.output().display(),
+        kept.join(", ")
+    );
+
+    // 校验的是剪切前的完整合并结果
+    finish(tools, job, &merged_args, message)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::stub::{self, StubScript};
+
+    #[test]
+    fn merge_job_runs_stub_ffmpeg() {
+        let ffmpeg = StubScript::new().progress().create_output();
+        let ffprobe = StubScript::new().stdout(&stub::probe_output(10.0, &[("video", "h264")]));
+        let tools = Tools {
+            ffmpeg: ffmpeg.install("ffmpeg"),
+            ffprobe: ffprobe.install("ffprobe"),
+            ..Default::default()
+        };
+        let output = ffmpeg.path("out.mp4");
+        let job = MergeJob {
+            args: MergeArgs::new("v.mp4", "a.m4a", &output),
+            audio: AudioFilters::default(),
+            segments: Vec::new(),
+            accurate_cut: false,
+            delete_sources: false,
+        };
+
+        let message = run(&tools, &job).unwrap();
+        assert!(
+            message.contains(&output.display().to_string()),
+            "{}",
+            message
+        );
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "stub");
+        assert_eq!(ffmpeg.calls("ffmpeg").len(), 1);
+        assert!(ffprobe.calls("ffprobe").is_empty());
+    }
+
+    #[test]
+    fn loudnorm_measures_before_merging() {
+        let ffmpeg = StubScript::new()
+            .stderr("{")
+            .stderr("\"input_i\" : \"-30.0\",")
+            .stderr("\"input_tp\" : \"-6.0\",")
+            .stderr("\"input_lra\" : \"5.0\",")
+            .stderr("\"input_thresh\" : \"-40.0\",")
+            .stderr("\"target_offset\" : \"0.5\"")
+            .stderr("}")
+            .create_output();
+        let tools = Tools {
+            ffmpeg: ffmpeg.install("ffmpeg"),
+            ..Default::default()
+        };
+        let job = MergeJob {
+            args: MergeArgs::new("v.mp4", "a.m4a", ffmpeg.path("out.mp4")),
+            audio: AudioFilters {
+                normalize: true,
+                ..Default::default()
+            },
+            segments: Vec::new(),
+            accurate_cut: false,
+            delete_sources: false,
+        };
+
+        run(&tools, &job).unwrap();
+        let calls = ffmpeg.calls("ffmpeg");
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].last().map(String::as_str), Some("-"));
+        let filter = calls[1].iter().skip_while(|a| *a != "-af").nth(1).unwrap();
+        assert!(filter.contains("measured_I=-30"), "{}", filter);
+        assert_eq!(
+            calls[1].last(),
+            Some(&ffmpeg.path("out.mp4").display().to_string())
+        );
+    }
+
+    #[test]
+    fn failed_verification_fails_the_job() {
+        let ffmpeg = StubScript::new().create_output();
+        let ffprobe = StubScript::new().exit_code(1);
+        let tools = Tools {
+            ffmpeg: ffmpeg.install("ffmpeg"),
+            ffprobe: ffprobe.install("ffprobe"),
+            ..Default::default()
+        };
+        let video = ffmpeg.path("v.mp4");
+        std::fs::write(&video, "video").unwrap();
+        let job = MergeJob {
+            args: MergeArgs::new(&video, "a.m4a", ffmpeg.path("out.mp4")),
+            audio: AudioFilters::default(),
+            segments: Vec::new(),
+            accurate_cut: false,
+            delete_sources: true,
+        };
+
+        let error = run(&tools, &job).unwrap_err();
+        assert!(error.contains("输出校验失败，已保留源文件"), "{}", error);
+        assert!(video.exists());
+        assert!(ffmpeg.path("out.mp4").exists());
+    }
+
+    #[test]
+    fn failed_merge_keeps_sources() {
+        let ffmpeg = StubScript::new().stderr("Conversion failed!").exit_code(1);
+        let tools = Tools {
+            ffmpeg: ffmpeg.install("ffmpeg"),
+            ..Default::default()
+        };
+        let video = ffmpeg.path("v.mp4");
+        std::fs::write(&video, "video").unwrap();
+        let job = MergeJob {
+            args: MergeArgs::new(&video, "a.m4a", ffmpeg.path("out.mp4")),
+            audio: AudioFilters::default(),
+            segments: Vec::new(),
+            accurate_cut: false,
+            delete_sources: true,
+        };
+
+        assert_eq!(
+            run(&tools, &job).unwrap_err(),
+            "转换失败（退出码 1）: Conversion failed!"
+        );
+        assert!(video.exists());
+        assert!(!ffmpeg.path("out.mp4").exists());
+    }
+}
@@ -0,0 +1,108 @@
+use chrono::{DateTime, Local};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// 最多保留的任务记录数
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Source {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Clone, Debug)]
+pub struct LogLine {
+    pub time: DateTime<Local>,
+    pub source: Source,
+    pub text: String,
+}
+
+// 一次 ffmpeg/ffprobe 调用的完整记录
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    pub action: String,
+    pub command: String,
+    pub started: DateTime<Local>,
+    pub lines: Vec<LogLine>,
+    // 启动失败或被信号终止时为 None
+    pub exit_code: Option<i32>,
+    pub duration: Duration,
+}
+
+impl LogEntry {
+    pub fn is_success(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    pub fn summary(&self) -> String {
+        let code = self
+            .exit_code
+            .map_or_else(|| "无".to_string(), |c| c.to_string());
+        format!(
+            "{} {} {}，退出码 {}，用时 {:.1} 秒",
+            self.started.format("%H:%M:%S"),
+            if self.is_success() { "✔" } else { "✖" },
+            self.action,
+            code,
+            self.duration.as_secs_f64()
+        )
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "== {} ==\n$ {}\n",
+            self.started.format("%Y-%m-%d %H:%M:%S"),
+            self.command
+        );
+        for line in &self.lines {
+            let tag = match line.source {
+                Source::Stdout => "out",
+                Source::Stderr => "err",
+            };
+            text.push_str(&format!(
+                "[{} {}] {}\n",
+                line.time.format("%H:%M:%S%.3f"),
+                tag,
+                line.text
+            ));
+        }
+        text.push_str(&format!("{}\n", self.summary()));
+        text
+    }
+}
+
+// 多处共享的任务日志，克隆后指向同一份记录
+#[derive(Clone, Debug, Default)]
+pub struct JobLog {
+    entries: Arc<Mutex<Vec<LogEntry>>>,
+}
+
+impl JobLog {
+    pub fn push(&self, entry: LogEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(entry);
+        let overflow = entries.len().saturating_sub(MAX_ENTRIES);
+        entries.drain(..overflow);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.entries.lock().unwrap().clone()
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn to_text(&self) -> String {
+        self.entries()
+            .iter()
+            .map(LogEntry::to_text)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
@@ -1,19 +1,112 @@
 #![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
+mod audio;
+mod cleanup;
+mod cli;
+mod concat;
+mod encode;
+mod extract;
+mod ffmpeg;
+mod job;
+mod log;
+mod metadata;
+mod naming;
+mod probe;
+mod remux;
+mod runner;
+#[cfg(test)]
+mod stub;
+mod thumbnail;
+mod tools;
+mod trim;
+
+use audio::{AudioFilters, Downmix};
 use eframe::egui;
+use encode::{AudioCodec, Profile, TranscodeArgs, VideoCodec};
+use ffmpeg::{Container, MergeArgs};
+use job::MergeJob;
+use metadata::{Chapter, Metadata, MetadataFile};
+use naming::Collision;
+use probe::{MediaInfo, StreamInfo};
+use remux::RemuxArgs;
 use rfd::FileDialog;
-#[cfg(windows)]
-use std::os::windows::process::CommandExt;
-use std::path::PathBuf;
-use std::process::Command;
-use uuid::Uuid;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tools::Tools;
+use trim::Segment;
+
+// 窗口标题，同时决定 eframe 持久化文件的位置
+pub const APP_NAME: &str = "FFmpeg 合并器";
+
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "avi", "mov", "ts", "flv", "webm"];
+
+// 工作模式
+#[derive(Default, PartialEq)]
+enum Mode {
+    #[default]
+    Merge,
+    Concat,
+    Extract,
+    Remux,
+    Transcode,
+}
+
+// 转码命令预览及生成它时的输入、预设与输出选项
+struct CommandPreview {
+    input: PathBuf,
+    profile: Profile,
+    container: Container,
+    collision: Collision,
+    command: Option<String>,
+}
+
+// 已选视频的缩略图与探测信息
+struct VideoPreview {
+    path: PathBuf,
+    texture: Result<egui::TextureHandle, String>,
+    info: Option<MediaInfo>,
+}
 
 #[derive(Default)]
 struct FFmpegApp {
+    mode: Mode,
     video_path: Option<PathBuf>,
+    video_preview: Option<VideoPreview>,
     audio_path: Option<PathBuf>,
     output_path: Option<PathBuf>,
+    container: Container,
+    name_template: String,
+    collision: Collision,
+    audio_delay_ms: i64,
+    shortest: bool,
     delete_orig: bool,
+    audio_filters: AudioFilters,
+    metadata: Metadata,
+    cover_path: Option<PathBuf>,
+    // 保留片段的开始/结束时间文本
+    segments: Vec<(String, String)>,
+    accurate_cut: bool,
+    // 拼接模式的片段列表与参数检查结果
+    clips: Vec<PathBuf>,
+    concat_check: Option<Result<(), String>>,
+    // 提取模式的输入文件及其各流的选中状态
+    extract_input: Option<PathBuf>,
+    extract_streams: Vec<(StreamInfo, bool)>,
+    // 转封装模式
+    remux_inputs: Vec<PathBuf>,
+    faststart: bool,
+    strip_data: bool,
+    strip_subtitles: bool,
+    // 转码模式的输入与可编辑的预设
+    transcode_inputs: Vec<PathBuf>,
+    profiles: Vec<Profile>,
+    profile_index: usize,
+    transcode_preview: Option<CommandPreview>,
     status_message: String,
+    // 已探测的文件时长
+    durations: HashMap<PathBuf, Option<f64>>,
+    tools: Tools,
+    // ffmpeg/ffprobe 可用性诊断
+    tools_check: Option<Result<String, String>>,
 }
 
 impl FFmpegApp {
@@ -56,189 +149,1255 @@ impl FFmpegApp {
 
         cc.egui_ctx.set_fonts(fonts);
 
-        Default::default()
+        // 读取上次保存的 ffmpeg 位置，没有则自动检测
+        let tools = cc
+            .storage
+            .and_then(|storage| {
+                Some(Tools {
+                    ffmpeg: storage.get_string("ffmpeg_path")?.into(),
+                    ffprobe: storage.get_string("ffprobe_path")?.into(),
+                    ..Default::default()
+                })
+            })
+            .unwrap_or_else(Tools::detect);
+        let tools_check = Some(tools.check());
+        // 预设文件不存在时读取旧版本保存在持久化存储中的预设
+        let profiles = encode::profiles_path()
+            .map(|path| encode::load_profiles(&path))
+            .filter(|profiles| !profiles.is_empty())
+            .or_else(|| {
+                cc.storage
+                    .and_then(|storage| storage.get_string(encode::PROFILES_KEY))
+                    .map(|text| encode::parse_profiles(&text))
+            })
+            .filter(|profiles| !profiles.is_empty())
+            .unwrap_or_else(Profile::builtin);
+
+        Self {
+            name_template: naming::DEFAULT_TEMPLATE.to_string(),
+            faststart: true,
+            strip_data: true,
+            profiles,
+            tools,
+            tools_check,
+            ..Default::default()
+        }
     }
 
     fn clear_state(&mut self) {
         self.video_path = None;
         self.audio_path = None;
         self.output_path = None;
+        self.audio_delay_ms = 0;
+        self.shortest = false;
         self.delete_orig = false;
+        self.audio_filters = AudioFilters::default();
+        self.metadata = Metadata::default();
+        self.cover_path = None;
+        self.segments.clear();
+        self.accurate_cut = false;
+        self.clips.clear();
+        self.concat_check = None;
+        self.extract_input = None;
+        self.extract_streams.clear();
+        self.remux_inputs.clear();
+        self.transcode_inputs.clear();
+        self.transcode_preview = None;
     }
 
-    fn get_default_output_path(&self) -> PathBuf {
-        let uuid = Uuid::new_v4().to_string();
-        if let Some(video_path) = &self.video_path {
-            // 使用视频文件所在的目录
-            if let Some(parent) = video_path.parent() {
-                return parent.join(format!("{}.mp4", uuid));
+    fn set_extract_input(&mut self, path: PathBuf) {
+        match probe::probe(&self.tools, &path) {
+            Some(info) => {
+                self.extract_streams = info.streams.into_iter().map(|s| (s, true)).collect();
+                self.status_message = format!("共 {} 条流", self.extract_streams.len());
+            }
+            None => {
+                self.extract_streams.clear();
+                self.status_message = format!("无法探测 {}", path.display());
             }
         }
-        // 如果无法获取视频文件目录，使用当前目录
-        PathBuf::from(format!("{}.mp4", uuid))
+        self.extract_input = Some(path);
     }
 
-    fn execute_ffmpeg(&mut self) {
-        if let (Some(video), Some(audio)) = (&self.video_path, &self.audio_path) {
-            // 如果没有设置输出路径，使用默认路径
-            let output = self
-                .output_path
-                .clone()
-                .unwrap_or_else(|| self.get_default_output_path());
-
-            let mut cmd = Command::new("ffmpeg");
-            cmd.arg("-i")
-                .arg(video)
-                .arg("-i")
-                .arg(audio)
-                .arg("-c")
-                .arg("copy")
-                .arg(&output);
-            #[cfg(windows)]
-            cmd.creation_flags(134_217_728u32);
-
-            let result = cmd.output();
-
-            match result {
-                Ok(s) => {
-                    if s.status.success() {
-                        self.status_message = format!("转换成功！输出文件：{}", output.display());
-
-                        if self.delete_orig {
-                            // 删除源文件
-                            let _ = std::fs::remove_file(self.video_path.as_ref().unwrap());
-                            let _ = std::fs::remove_file(self.audio_path.as_ref().unwrap());
-                        }
-                    } else {
-                        self.status_message =
-                            format!("转换失败: {}", String::from_utf8_lossy(&s.stderr));
-                    }
+    fn parse_segments(&self) -> Result<Vec<Segment>, String> {
+        self.segments
+            .iter()
+            .map(|(start, end)| {
+                let start = if start.trim().is_empty() {
+                    Some(0.0)
+                } else {
+                    trim::parse_time(start)
+                };
+                let end = if end.trim().is_empty() {
+                    Some(None)
+                } else {
+                    trim::parse_time(end).map(Some)
+                };
+                start
+                    .zip(end)
+                    .and_then(|(start, end)| Segment::new(start, end))
+                    .ok_or_else(|| "无效的片段时间".to_string())
+            })
+            .collect()
+    }
+
+    // 视频变化后重新截图，结果按路径缓存
+    fn preview_ui(&mut self, ui: &mut egui::Ui) {
+        let Some(path) = self.video_path.clone() else {
+            return;
+        };
+        if self.video_preview.as_ref().is_none_or(|p| p.path != path) {
+            let info = probe::probe(&self.tools, &path);
+            let duration = info.as_ref().and_then(|i| i.duration);
+            let texture = thumbnail::thumbnail(&self.tools, &path, duration).map(|thumb| {
+                let image = egui::ColorImage::from_rgba_unmultiplied(
+                    [thumb.width as usize, thumb.height as usize],
+                    &thumb.rgba,
+                );
+                ui.ctx()
+                    .load_texture("video_preview", image, egui::TextureOptions::LINEAR)
+            });
+            self.video_preview = Some(VideoPreview {
+                path,
+                texture,
+                info,
+            });
+        }
+
+        let Some(preview) = &self.video_preview else {
+            return;
+        };
+        ui.horizontal(|ui| {
+            match &preview.texture {
+                Ok(texture) => {
+                    ui.add(egui::Image::new(texture).max_width(200.0));
                 }
                 Err(e) => {
-                    self.status_message = format!("执行错误: {}", e);
+                    ui.colored_label(egui::Color32::YELLOW, e);
                 }
             }
-        }
+            ui.vertical(|ui| {
+                let Some(info) = &preview.info else {
+                    ui.label("无法探测视频信息");
+                    return;
+                };
+                if let Some(duration) = info.duration {
+                    ui.label(format!("时长 {}", trim::format_time(duration)));
+                }
+                if let Some((width, height)) = info.resolution() {
+                    ui.label(format!("分辨率 {}x{}", width, height));
+                }
+                if let Some(video) = info.first_stream("video") {
+                    ui.label(format!("编码 {}", video.codec_name));
+                }
+            });
+        });
     }
-}
 
-impl eframe::App for FFmpegApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("FFmpeg 视频/音频合并");
-            ui.add_space(10.0);
-            ui.label("拖动文件到窗口，自动识别");
-            ui.add_space(20.0);
+    fn audio_ui(&mut self, ui: &mut egui::Ui) {
+        let filters = &mut self.audio_filters;
+        egui::CollapsingHeader::new("音频处理").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut filters.normalize, "响度标准化（EBU R128，两遍）");
+                ui.add_enabled(
+                    filters.normalize,
+                    egui::DragValue::new(&mut filters.target_lufs)
+                        .speed(0.5)
+                        .range(-70.0..=-5.0)
+                        .suffix(" LUFS"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("音量增益");
+                ui.add_enabled(
+                    !filters.normalize,
+                    egui::DragValue::new(&mut filters.gain_db)
+                        .speed(0.5)
+                        .range(-30.0..=30.0)
+                        .suffix(" dB"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("声道");
+                ui.radio_value(&mut filters.downmix, Downmix::Keep, "保持");
+                ui.radio_value(&mut filters.downmix, Downmix::Stereo, "立体声");
+                ui.radio_value(&mut filters.downmix, Downmix::Mono, "单声道");
+            });
+            if !filters.is_empty() {
+                ui.label("音频将重新编码为 AAC，视频仍为流复制");
+            }
+        });
+    }
 
-            // 文件拖放处理
-            if !ctx.input(|i| i.raw.dropped_files.is_empty()) {
-                let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
-                for file in dropped_files {
-                    if let Some(path) = file.path {
-                        let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
-
-                        match extension.to_lowercase().as_str() {
-                            "mp4" | "mkv" | "avi" if self.video_path.is_none() => {
-                                self.video_path = Some(path);
-                            }
-                            "m4a" | "mp3" | "aac" if self.audio_path.is_none() => {
-                                self.audio_path = Some(path);
-                            }
-                            _ => {}
-                        }
+    fn trim_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("剪切片段").show(ui, |ui| {
+            let mut remove = None;
+            for (i, (start, end)) in self.segments.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(start)
+                            .hint_text("00:00:00")
+                            .desired_width(90.0),
+                    );
+                    ui.label("-");
+                    ui.add(
+                        egui::TextEdit::singleline(end)
+                            .hint_text("结尾")
+                            .desired_width(90.0),
+                    );
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(i);
                     }
+                });
+            }
+            if let Some(i) = remove {
+                self.segments.remove(i);
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("添加片段").clicked() {
+                    self.segments.push(Default::default());
                 }
+                ui.checkbox(&mut self.accurate_cut, "精确剪切")
+                    .on_hover_text(
+                        "从指定时间开始剪切。H.264/HEVC 只以高质量重新编码起点到下一个关键帧之间的画面，\
+                         其余部分流复制（只保留视频与音频）；其他编码或音频无法写入 MPEG-TS 时重新编码整个片段，耗时较长",
+                    );
+            });
+        });
+    }
+
+    fn import_metadata(&mut self, source: Option<PathBuf>) {
+        let result = match source {
+            Some(input) => metadata::extract(&self.tools, &input),
+            None => match FileDialog::new()
+                .add_filter("FFMETADATA", &["txt", "ffmeta"])
+                .pick_file()
+            {
+                Some(path) => metadata::read_file(&path),
+                None => return,
+            },
+        };
+        match result {
+            Ok(metadata) => {
+                self.status_message = format!("已导入 {} 个章节", metadata.chapters.len());
+                self.metadata = metadata;
             }
+            Err(e) => self.status_message = e,
+        }
+    }
+
+    fn metadata_ui(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("元数据与章节").show(ui, |ui| {
+            egui::Grid::new("metadata").num_columns(2).show(ui, |ui| {
+                for (label, value) in [
+                    ("标题", &mut self.metadata.title),
+                    ("艺术家", &mut self.metadata.artist),
+                    ("备注", &mut self.metadata.comment),
+                ] {
+                    ui.label(label);
+                    ui.text_edit_singleline(value);
+                    ui.end_row();
+                }
+            });
 
-            // 视频文件选择
             ui.horizontal(|ui| {
-                if ui.button("选择视频文件").clicked() {
+                if ui.button("选择封面").clicked() {
                     if let Some(path) = FileDialog::new()
-                        .add_filter("视频文件", &["mp4", "mkv", "avi"])
+                        .add_filter("图片", &["jpg", "jpeg", "png"])
                         .pick_file()
                     {
-                        self.video_path = Some(path);
+                        self.cover_path = Some(path);
                     }
                 }
-                if let Some(path) = &self.video_path {
+                if let Some(path) = &self.cover_path {
                     ui.label(path.file_name().unwrap().to_string_lossy().to_string());
+                    if ui.small_button("✖").clicked() {
+                        self.cover_path = None;
+                    }
                 }
             });
 
-            ui.add_space(10.0);
-            // 音频文件选择
+            ui.add_space(5.0);
+            ui.label("章节（秒）");
+            let mut remove = None;
+            egui::Grid::new("chapters").num_columns(4).show(ui, |ui| {
+                for (i, chapter) in self.metadata.chapters.iter_mut().enumerate() {
+                    ui.add(
+                        egui::DragValue::new(&mut chapter.start)
+                            .speed(0.1)
+                            .range(0.0..=f64::MAX),
+                    );
+                    ui.add(
+                        egui::DragValue::new(&mut chapter.end)
+                            .speed(0.1)
+                            .range(0.0..=f64::MAX),
+                    );
+                    ui.text_edit_singleline(&mut chapter.title);
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(i) = remove {
+                self.metadata.chapters.remove(i);
+            }
+
             ui.horizontal(|ui| {
-                if ui.button("选择音频文件").clicked() {
+                if ui.button("添加章节").clicked() {
+                    let start = self.metadata.chapters.last().map_or(0.0, |c| c.end);
+                    self.metadata.chapters.push(Chapter {
+                        start,
+                        end: start,
+                        title: format!("章节 {}", self.metadata.chapters.len() + 1),
+                    });
+                }
+                if ui.button("从视频导入").clicked() && self.video_path.is_some() {
+                    self.import_metadata(self.video_path.clone());
+                }
+                if ui.button("从音频导入").clicked() && self.audio_path.is_some() {
+                    self.import_metadata(self.audio_path.clone());
+                }
+                if ui.button("从文件导入").clicked() {
+                    self.import_metadata(None);
+                }
+                if ui.button("导出").clicked() {
                     if let Some(path) = FileDialog::new()
-                        .add_filter("音频文件", &["m4a", "mp3", "aac"])
-                        .pick_file()
+                        .add_filter("FFMETADATA", &["txt", "ffmeta"])
+                        .save_file()
                     {
-                        self.audio_path = Some(path);
+                        self.status_message = match metadata::write_file(&path, &self.metadata) {
+                            Ok(()) => format!("元数据已导出到 {}", path.display()),
+                            Err(e) => e,
+                        };
                     }
                 }
-                if let Some(path) = &self.audio_path {
-                    ui.label(path.file_name().unwrap().to_string_lossy().to_string());
+            });
+        });
+    }
+
+    fn duration_of(&mut self, path: &Path) -> Option<f64> {
+        *self
+            .durations
+            .entry(path.to_path_buf())
+            .or_insert_with(|| probe::duration(&self.tools, path))
+    }
+
+    fn check_tools(&mut self) {
+        self.tools_check = Some(self.tools.check());
+        self.durations.clear();
+        self.video_preview = None;
+    }
+
+    fn settings_ui(&mut self, ui: &mut egui::Ui) {
+        let tools_ok = matches!(self.tools_check, Some(Ok(_)));
+        egui::CollapsingHeader::new("ffmpeg 设置")
+            .default_open(!tools_ok)
+            .show(ui, |ui| {
+                let mut changed = false;
+                for (name, path) in [
+                    ("ffmpeg", &mut self.tools.ffmpeg),
+                    ("ffprobe", &mut self.tools.ffprobe),
+                ] {
+                    ui.horizontal(|ui| {
+                        ui.label(name);
+                        let mut text = path.display().to_string();
+                        if ui.text_edit_singleline(&mut text).changed() {
+                            *path = text.into();
+                        }
+                        if ui.button("浏览").clicked() {
+                            if let Some(picked) = FileDialog::new().pick_file() {
+                                *path = picked;
+                                changed = true;
+                            }
+                        }
+                    });
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("自动检测").clicked() {
+                        // 保留已有日志
+                        self.tools = Tools {
+                            log: self.tools.log.clone(),
+                            ..Tools::detect()
+                        };
+                        changed = true;
+                    }
+                    if ui.button("检查").clicked() {
+                        changed = true;
+                    }
+                });
+                if changed {
+                    self.check_tools();
                 }
             });
 
-            ui.add_space(10.0);
-            // 输出文件选择
+        match &self.tools_check {
+            Some(Ok(version)) => {
+                ui.weak(version);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    format!("⚠ {}，请在设置中指定 ffmpeg 位置", e),
+                );
+            }
+            None => {}
+        }
+    }
+
+    fn log_ui(&mut self, ui: &mut egui::Ui) {
+        let title = format!("日志（{}）", self.tools.log.len());
+        egui::CollapsingHeader::new(title).show(ui, |ui| {
             ui.horizontal(|ui| {
-                if ui.button("选择输出位置").clicked() {
+                if ui.button("复制全部").clicked() {
+                    ui.ctx().copy_text(self.tools.log.to_text());
+                }
+                if ui.button("保存到文件").clicked() {
                     if let Some(path) = FileDialog::new()
-                        .add_filter("MP4文件", &["mp4"])
+                        .add_filter("日志", &["log", "txt"])
+                        .set_file_name("ffmerge.log")
                         .save_file()
                     {
-                        self.output_path = Some(path);
+                        self.status_message = match std::fs::write(&path, self.tools.log.to_text())
+                        {
+                            Ok(()) => format!("日志已保存到 {}", path.display()),
+                            Err(e) => format!("无法保存日志: {}", e),
+                        };
                     }
                 }
-                if let Some(path) = &self.output_path {
-                    ui.label(path.file_name().unwrap().to_string_lossy().to_string());
+                if ui.button("清空").clicked() {
+                    self.tools.log.clear();
                 }
             });
 
-            ui.add_space(10.0);
-            ui.checkbox(&mut self.delete_orig, "完成后删除源文件❗");
-            ui.add_space(10.0);
+            // 最新的记录在最上方，失败的默认展开
+            let entries = self.tools.log.entries();
+            for (i, entry) in entries.iter().enumerate().rev() {
+                let header = egui::RichText::new(entry.summary()).color(if entry.is_success() {
+                    ui.visuals().text_color()
+                } else {
+                    egui::Color32::LIGHT_RED
+                });
+                egui::CollapsingHeader::new(header)
+                    .id_salt(("log", i, entry.started))
+                    .default_open(!entry.is_success())
+                    .show(ui, |ui| {
+                        if ui.small_button("复制").clicked() {
+                            ui.ctx().copy_text(entry.to_text());
+                        }
+                        let mut text = entry.to_text();
+                        egui::ScrollArea::vertical()
+                            .id_salt(("log_text", i, entry.started))
+                            .max_height(200.0)
+                            .show(ui, |ui| {
+                                ui.add(
+                                    egui::TextEdit::multiline(&mut text)
+                                        .code_editor()
+                                        .desired_width(f32::INFINITY),
+                                );
+                            });
+                    });
+            }
+        });
+    }
+
+    fn duration_warning(&mut self) -> Option<String> {
+        let video = self.video_path.clone()?;
+        let audio = self.audio_path.clone()?;
+        let video = self.duration_of(&video)?;
+        let audio = self.duration_of(&audio)?;
+        probe::duration_mismatch(video, audio, self.audio_delay_ms).map(probe::mismatch_warning)
+    }
+
+    fn handle_dropped(&mut self, paths: Vec<PathBuf>) {
+        for path in paths {
+            let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+
+            match (&self.mode, extension.to_lowercase().as_str()) {
+                (Mode::Merge, "mp4" | "mkv" | "avi") if self.video_path.is_none() => {
+                    self.video_path = Some(path);
+                }
+                (Mode::Merge, "m4a" | "mp3" | "aac") if self.audio_path.is_none() => {
+                    self.audio_path = Some(path);
+                }
+                (Mode::Concat, ext) if VIDEO_EXTENSIONS.contains(&ext) => {
+                    self.clips.push(path);
+                    self.concat_check = None;
+                }
+                (Mode::Extract, _) => self.set_extract_input(path),
+                (Mode::Remux, ext) if VIDEO_EXTENSIONS.contains(&ext) => {
+                    self.remux_inputs.push(path);
+                }
+                (Mode::Transcode, ext) if VIDEO_EXTENSIONS.contains(&ext) => {
+                    self.transcode_inputs.push(path);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn merge_ui(&mut self, ui: &mut egui::Ui) {
+        // 视频文件选择
+        ui.horizontal(|ui| {
+            if ui.button("选择视频文件").clicked() {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("视频文件", &["mp4", "mkv", "avi"])
+                    .pick_file()
+                {
+                    self.video_path = Some(path);
+                }
+            }
+            if let Some(path) = &self.video_path {
+                ui.label(path.file_name().unwrap().to_string_lossy().to_string());
+            }
+        });
+        self.preview_ui(ui);
 
-            ui.separator();
-            ui.add_space(20.0);
+        ui.add_space(10.0);
+        // 音频文件选择
+        ui.horizontal(|ui| {
+            if ui.button("选择音频文件").clicked() {
+                if let Some(path) = FileDialog::new()
+                    .add_filter("音频文件", &["m4a", "mp3", "aac"])
+                    .pick_file()
+                {
+                    self.audio_path = Some(path);
+                }
+            }
+            if let Some(path) = &self.audio_path {
+                ui.label(path.file_name().unwrap().to_string_lossy().to_string());
+            }
+        });
+
+        ui.add_space(10.0);
+        // 输出文件选择
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("container")
+                .width(60.0)
+                .selected_text(self.container.extension())
+                .show_ui(ui, |ui| {
+                    for container in Container::ALL {
+                        ui.selectable_value(&mut self.container, container, container.extension());
+                    }
+                });
+            if ui.button("选择输出位置").clicked() {
+                let ext = self.container.extension();
+                if let Some(path) = FileDialog::new()
+                    .add_filter(format!("{}文件", ext.to_uppercase()), &[ext])
+                    .save_file()
+                {
+                    self.output_path = Some(path);
+                }
+            }
+            if let Some(path) = &self.output_path {
+                ui.label(path.file_name().unwrap().to_string_lossy().to_string());
+            }
+        });
+        if self.output_path.is_none() {
+            // 未选择输出位置时按模板命名
             ui.horizontal(|ui| {
-                // 执行按钮
-                let can_execute = self.video_path.is_some() && self.audio_path.is_some();
+                ui.label("输出命名");
+                ui.text_edit_singleline(&mut self.name_template)
+                    .on_hover_text(naming::TEMPLATE_HELP);
+            });
+            ui.horizontal(|ui| {
+                ui.label("文件已存在时");
+                ui.radio_value(&mut self.collision, Collision::Suffix, "添加序号");
+                ui.radio_value(&mut self.collision, Collision::Overwrite, "覆盖");
+                ui.radio_value(&mut self.collision, Collision::Skip, "跳过");
+            });
+        }
+
+        ui.add_space(10.0);
+        // 音画同步
+        ui.horizontal(|ui| {
+            ui.label("音频延迟");
+            ui.add(
+                egui::DragValue::new(&mut self.audio_delay_ms)
+                    .speed(10)
+                    .suffix(" ms"),
+            );
+            ui.checkbox(&mut self.shortest, "按最短的流截断");
+        });
+        if let Some(warning) = self.duration_warning() {
+            ui.colored_label(egui::Color32::YELLOW, warning);
+        }
+
+        ui.add_space(10.0);
+        self.metadata_ui(ui);
+        self.audio_ui(ui);
+        self.trim_ui(ui);
+
+        ui.add_space(10.0);
+        ui.checkbox(&mut self.delete_orig, "完成后将源文件移至回收站❗");
+        ui.add_space(10.0);
+
+        ui.separator();
+        ui.add_space(20.0);
+        ui.horizontal(|ui| {
+            // 执行按钮
+            let can_execute = self.video_path.is_some()
+                && self.audio_path.is_some()
+                && matches!(self.tools_check, Some(Ok(_)));
+
+            if ui
+                .add_enabled(can_execute, egui::Button::new("开始处理"))
+                .clicked()
+            {
+                self.execute_ffmpeg();
+                self.clear_state();
+            }
+
+            // 清除按钮
+            if ui.button("清除选择").clicked() {
+                self.clear_state();
+            }
+        });
+    }
+
+    fn concat_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("添加片段").clicked() {
+                if let Some(paths) = FileDialog::new()
+                    .add_filter("视频文件", VIDEO_EXTENSIONS)
+                    .pick_files()
+                {
+                    self.clips.extend(paths);
+                    self.concat_check = None;
+                }
+            }
+            if ui.button("选择输出位置").clicked() {
+                if let Some(path) = FileDialog::new().save_file() {
+                    self.output_path = Some(path);
+                }
+            }
+            if let Some(path) = &self.output_path {
+                ui.label(path.file_name().unwrap().to_string_lossy().to_string());
+            }
+        });
 
+        ui.add_space(10.0);
+        // 片段列表，可调整顺序
+        let mut action = None;
+        let count = self.clips.len();
+        for (i, clip) in self.clips.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(i > 0, egui::Button::new("⬆").small())
+                    .clicked()
+                {
+                    action = Some((i, i - 1));
+                }
                 if ui
-                    .add_enabled(can_execute, egui::Button::new("开始处理"))
+                    .add_enabled(i + 1 < count, egui::Button::new("⬇").small())
                     .clicked()
                 {
-                    self.execute_ffmpeg();
-                    self.clear_state();
+                    action = Some((i, i + 1));
                 }
+                if ui.small_button("✖").clicked() {
+                    action = Some((i, usize::MAX));
+                }
+                ui.label(format!(
+                    "{}. {}",
+                    i + 1,
+                    clip.file_name().unwrap().to_string_lossy()
+                ));
+            });
+        }
+        match action {
+            Some((i, usize::MAX)) => {
+                self.clips.remove(i);
+                self.concat_check = None;
+            }
+            Some((i, j)) => {
+                self.clips.swap(i, j);
+                self.concat_check = None;
+            }
+            None => {}
+        }
+
+        ui.add_space(10.0);
+        match &self.concat_check {
+            Some(Ok(())) => {
+                ui.label("✔ 参数一致，将无损拼接");
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::YELLOW, format!("⚠ {}，将重新编码", e));
+            }
+            None => {}
+        }
 
-                // 清除按钮
-                if ui.button("清除选择").clicked() {
-                    self.clear_state();
+        ui.separator();
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            let tools_ok = matches!(self.tools_check, Some(Ok(_)));
+            if ui
+                .add_enabled(tools_ok && count >= 2, egui::Button::new("检查参数"))
+                .clicked()
+            {
+                self.concat_check = Some(
+                    concat::probe_all(&self.tools, &self.clips)
+                        .and_then(|infos| concat::check_compatible(&self.clips, &infos)),
+                );
+            }
+            if ui
+                .add_enabled(tools_ok && count >= 2, egui::Button::new("开始拼接"))
+                .clicked()
+            {
+                self.execute_concat();
+                self.clear_state();
+            }
+            if ui.button("清除选择").clicked() {
+                self.clear_state();
+            }
+        });
+    }
+
+    fn extract_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("选择文件").clicked() {
+                if let Some(path) = FileDialog::new().pick_file() {
+                    self.set_extract_input(path);
                 }
+            }
+            if let Some(path) = &self.extract_input {
+                ui.label(path.file_name().unwrap().to_string_lossy().to_string());
+            }
+        });
+
+        ui.add_space(10.0);
+        for (stream, selected) in &mut self.extract_streams {
+            ui.checkbox(selected, stream.describe());
+        }
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("文件已存在时");
+            ui.radio_value(&mut self.collision, Collision::Suffix, "添加序号");
+            ui.radio_value(&mut self.collision, Collision::Overwrite, "覆盖");
+            ui.radio_value(&mut self.collision, Collision::Skip, "跳过");
+        });
+
+        ui.separator();
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            let can_execute = matches!(self.tools_check, Some(Ok(_)))
+                && self.extract_streams.iter().any(|(_, selected)| *selected);
+            if ui
+                .add_enabled(can_execute, egui::Button::new("开始提取"))
+                .clicked()
+            {
+                self.execute_extract();
+                self.clear_state();
+            }
+            if ui.button("清除选择").clicked() {
+                self.clear_state();
+            }
+        });
+    }
+
+    fn execute_extract(&mut self) {
+        let Some(input) = &self.extract_input else {
+            return;
+        };
+        let streams: Vec<StreamInfo> = self
+            .extract_streams
+            .iter()
+            .filter(|(_, selected)| *selected)
+            .map(|(stream, _)| stream.clone())
+            .collect();
+
+        self.status_message = match extract::extract(&self.tools, input, &streams, self.collision) {
+            Ok(outputs) => format!(
+                "提取完成，共 {} 个文件：\n{}",
+                outputs.len(),
+                outputs
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+            Err(e) => e,
+        };
+    }
+
+    fn remux_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("选择文件").clicked() {
+                if let Some(paths) = FileDialog::new()
+                    .add_filter("视频文件", VIDEO_EXTENSIONS)
+                    .pick_files()
+                {
+                    self.remux_inputs.extend(paths);
+                }
+            }
+            ui.label(format!("已选择 {} 个文件", self.remux_inputs.len()));
+        });
+        for path in &self.remux_inputs {
+            ui.label(path.file_name().unwrap().to_string_lossy().to_string());
+        }
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("目标格式");
+            for container in Container::ALL {
+                ui.radio_value(&mut self.container, container, container.extension());
+            }
+        });
+        ui.add_enabled(
+            self.container != Container::Mkv,
+            egui::Checkbox::new(&mut self.faststart, "快速启动（+faststart，适合网页播放）"),
+        );
+        ui.checkbox(&mut self.strip_data, "去除数据流");
+        ui.checkbox(&mut self.strip_subtitles, "去除字幕");
+        ui.horizontal(|ui| {
+            ui.label("文件已存在时");
+            ui.radio_value(&mut self.collision, Collision::Suffix, "添加序号");
+            ui.radio_value(&mut self.collision, Collision::Overwrite, "覆盖");
+            ui.radio_value(&mut self.collision, Collision::Skip, "跳过");
+        });
+
+        ui.separator();
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            let can_execute =
+                matches!(self.tools_check, Some(Ok(_))) && !self.remux_inputs.is_empty();
+            if ui
+                .add_enabled(can_execute, egui::Button::new("开始转封装"))
+                .clicked()
+            {
+                self.execute_remux();
+                self.clear_state();
+            }
+            if ui.button("清除选择").clicked() {
+                self.clear_state();
+            }
+        });
+    }
+
+    fn execute_remux(&mut self) {
+        let results: Vec<String> = self
+            .remux_inputs
+            .iter()
+            .map(|input| {
+                let name = input.file_name().unwrap().to_string_lossy();
+                let Some(output) = remux::output_for(input, self.container, self.collision) else {
+                    return format!("{}: 输出文件已存在，已跳过", name);
+                };
+                let args = RemuxArgs::new(input, output, self.container)
+                    .faststart(self.faststart)
+                    .strip_data(self.strip_data)
+                    .strip_subtitles(self.strip_subtitles)
+                    .overwrite(self.collision == Collision::Overwrite)
+                    .streams(probe::probe(&self.tools, input).map(|info| info.streams));
+                let mut lines = vec![match remux::remux(&self.tools, &args) {
+                    Ok(output) => format!("{} → {}", name, output.display()),
+                    Err(e) => format!("{}: {}", name, e),
+                }];
+                lines.extend(args.warnings().iter().map(|w| format!("{}: {}", name, w)));
+                lines.join("\n")
+            })
+            .collect();
+        self.status_message = results.join("\n");
+    }
+
+    fn profile_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("预设");
+            egui::ComboBox::from_id_salt("profile")
+                .selected_text(self.profiles[self.profile_index].name.clone())
+                .show_ui(ui, |ui| {
+                    for (i, profile) in self.profiles.iter().enumerate() {
+                        ui.selectable_value(&mut self.profile_index, i, &profile.name)
+                            .on_hover_text(profile.describe());
+                    }
+                });
+            if ui.button("另存为新预设").clicked() {
+                let mut profile = self.profiles[self.profile_index].clone();
+                profile.name = format!("{} 副本", profile.name);
+                self.profiles.push(profile);
+                self.profile_index = self.profiles.len() - 1;
+            }
+            if ui
+                .add_enabled(self.profiles.len() > 1, egui::Button::new("删除"))
+                .clicked()
+            {
+                self.profiles.remove(self.profile_index);
+                self.profile_index = self.profile_index.min(self.profiles.len() - 1);
+            }
+            if ui.button("恢复默认").clicked() {
+                self.profiles = Profile::builtin();
+                self.profile_index = 0;
+            }
+        });
+
+        let profile = &mut self.profiles[self.profile_index];
+        egui::Grid::new("profile").num_columns(2).show(ui, |ui| {
+            ui.label("名称");
+            ui.text_edit_singleline(&mut profile.name);
+            ui.end_row();
+
+            ui.label("视频");
+            let mut video = profile.video;
+            egui::ComboBox::from_id_salt("video_codec")
+                .selected_text(video.describe())
+                .show_ui(ui, |ui| {
+                    for codec in VideoCodec::ALL {
+                        ui.selectable_value(&mut video, codec, codec.describe());
+                    }
+                });
+            if video != profile.video {
+                profile.set_video(video);
+            }
+            ui.end_row();
+
+            if profile.video != VideoCodec::Copy {
+                ui.label("CRF");
+                ui.add(egui::Slider::new(
+                    &mut profile.crf,
+                    0..=profile.video.max_crf(),
+                ));
+                ui.end_row();
+
+                ui.label("速度");
+                egui::ComboBox::from_id_salt("speed")
+                    .selected_text(profile.speed.clone())
+                    .show_ui(ui, |ui| {
+                        for speed in profile.video.speeds() {
+                            ui.selectable_value(&mut profile.speed, speed.to_string(), *speed);
+                        }
+                    });
+                ui.end_row();
+
+                ui.label("最大高度");
+                ui.add(egui::DragValue::new(&mut profile.max_height).suffix(" p（0 不限）"));
+                ui.end_row();
+
+                ui.label("最大帧率");
+                ui.add(egui::DragValue::new(&mut profile.max_fps).suffix(" fps（0 不限）"));
+                ui.end_row();
+            }
+
+            ui.label("音频");
+            egui::ComboBox::from_id_salt("audio_codec")
+                .selected_text(profile.audio.describe())
+                .show_ui(ui, |ui| {
+                    for codec in AudioCodec::ALL {
+                        ui.selectable_value(&mut profile.audio, codec, codec.describe());
+                    }
+                });
+            ui.end_row();
+
+            if profile.audio != AudioCodec::Copy {
+                ui.label("音频码率");
+                ui.add(
+                    egui::DragValue::new(&mut profile.audio_bitrate)
+                        .range(32..=512)
+                        .suffix(" kbps"),
+                );
+                ui.end_row();
+            }
+        });
+    }
+
+    // 输入对应的转码参数，输出已存在且选择跳过时返回 None
+    fn transcode_args(&self, input: &Path) -> Option<TranscodeArgs> {
+        let profile = &self.profiles[self.profile_index];
+        naming::resolve_collision(
+            encode::default_output(input, self.container),
+            self.collision,
+        )
+        .map(|output| {
+            TranscodeArgs::new(input, output, self.container, profile.clone())
+                .overwrite(self.collision == Collision::Overwrite)
+        })
+    }
+
+    fn transcode_jobs(&self) -> Vec<(PathBuf, Option<TranscodeArgs>)> {
+        self.transcode_inputs
+            .iter()
+            .map(|input| (input.clone(), self.transcode_args(input)))
+            .collect()
+    }
+
+    // 第一个文件的命令行，只在输入、预设或输出选项变化时重新生成
+    fn transcode_command(&mut self) -> Option<String> {
+        let input = self.transcode_inputs.first()?;
+        let profile = &self.profiles[self.profile_index];
+        let stale = self.transcode_preview.as_ref().is_none_or(|p| {
+            p.input != *input
+                || p.profile != *profile
+                || p.container != self.container
+                || p.collision != self.collision
+        });
+        if stale {
+            self.transcode_preview = Some(CommandPreview {
+                input: input.clone(),
+                profile: profile.clone(),
+                container: self.container,
+                collision: self.collision,
+                command: self
+                    .transcode_args(input)
+                    .map(|args| self.tools.ffmpeg_command_line(&args.build())),
+            });
+        }
+        self.transcode_preview.as_ref()?.command.clone()
+    }
+
+    fn transcode_ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("选择文件").clicked() {
+                if let Some(paths) = FileDialog::new()
+                    .add_filter("视频文件", VIDEO_EXTENSIONS)
+                    .pick_files()
+                {
+                    self.transcode_inputs.extend(paths);
+                }
+            }
+            ui.label(format!("已选择 {} 个文件", self.transcode_inputs.len()));
+        });
+        for path in &self.transcode_inputs {
+            ui.label(path.file_name().unwrap().to_string_lossy().to_string());
+        }
+
+        ui.add_space(10.0);
+        self.profile_ui(ui);
+
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            ui.label("输出格式");
+            for container in Container::ALL {
+                ui.radio_value(&mut self.container, container, container.extension());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("文件已存在时");
+            ui.radio_value(&mut self.collision, Collision::Suffix, "添加序号");
+            ui.radio_value(&mut self.collision, Collision::Overwrite, "覆盖");
+            ui.radio_value(&mut self.collision, Collision::Skip, "跳过");
+        });
+
+        // 预览第一个文件的命令行
+        if let Some(mut command) = self.transcode_command() {
+            ui.add_space(5.0);
+            ui.label("命令预览");
+            ui.add(
+                egui::TextEdit::multiline(&mut command)
+                    .code_editor()
+                    .desired_rows(2)
+                    .desired_width(f32::INFINITY),
+            );
+        }
+
+        ui.separator();
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            let can_execute =
+                matches!(self.tools_check, Some(Ok(_))) && !self.transcode_inputs.is_empty();
+            if ui
+                .add_enabled(can_execute, egui::Button::new("开始转码"))
+                .clicked()
+            {
+                self.execute_transcode();
+                self.clear_state();
+            }
+            if ui.button("清除选择").clicked() {
+                self.clear_state();
+            }
+        });
+    }
+
+    fn execute_transcode(&mut self) {
+        let results: Vec<String> = self
+            .transcode_jobs()
+            .into_iter()
+            .map(|(input, args)| {
+                let name = input.file_name().unwrap().to_string_lossy().to_string();
+                let Some(args) = args else {
+                    return format!("{}: 输出文件已存在，已跳过", name);
+                };
+                match encode::transcode(&self.tools, &args) {
+                    Ok(output) => format!("{} → {}", name, output.display()),
+                    Err(e) => format!("{}: {}", name, e),
+                }
+            })
+            .collect();
+        self.status_message = results.join("\n");
+    }
+
+    fn execute_concat(&mut self) {
+        let Some(first) = self.clips.first() else {
+            return;
+        };
+        let (output, overwrite) = match &self.output_path {
+            Some(path) => (path.clone(), true),
+            None => {
+                let ext = first.extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+                let path = naming::derived_path(first, "concat", ext);
+                let Some(path) = naming::resolve_collision(path, self.collision) else {
+                    self.status_message = "输出文件已存在，已跳过".to_string();
+                    return;
+                };
+                (path, self.collision == Collision::Overwrite)
+            }
+        };
+
+        self.status_message = match concat::concat(&self.tools, &self.clips, &output, overwrite) {
+            Ok(result) => {
+                let message = format!(
+                    "拼接成功（{}）！输出文件：{}",
+                    result.method.describe(),
+                    output.display()
+                );
+                match result.warning {
+                    Some(warning) => format!("{}\n{}", message, warning),
+                    None => message,
+                }
+            }
+            Err(e) => e,
+        };
+    }
+
+    fn execute_ffmpeg(&mut self) {
+        if let (Some(video), Some(audio)) = (&self.video_path, &self.audio_path) {
+            // 手动选择的输出路径已在对话框中确认覆盖，否则按模板生成
+            let (output, overwrite) = match &self.output_path {
+                Some(path) => (path.clone(), true),
+                None => {
+                    let path = naming::output_path(
+                        &self.tools,
+                        &self.name_template,
+                        video,
+                        Some(audio),
+                        self.container,
+                    );
+                    let Some(path) = naming::resolve_collision(path, self.collision) else {
+                        self.status_message = "输出文件已存在，已跳过".to_string();
+                        return;
+                    };
+                    (path, self.collision == Collision::Overwrite)
+                }
+            };
+            // 临时元数据文件在合并结束后删除
+            let metadata_file = if self.metadata.is_empty() {
+                None
+            } else {
+                match MetadataFile::create(&self.metadata) {
+                    Ok(file) => Some(file),
+                    Err(e) => {
+                        self.status_message = e;
+                        return;
+                    }
+                }
+            };
+            let args = MergeArgs::new(video, audio, output)
+                .container(Some(self.container))
+                .audio_delay(self.audio_delay_ms)
+                .shortest(self.shortest)
+                .overwrite(overwrite)
+                .metadata_file(metadata_file.as_ref().map(|f| f.path().to_path_buf()))
+                .cover(self.cover_path.clone());
+
+            let segments = match self.parse_segments() {
+                Ok(segments) => segments,
+                Err(e) => {
+                    self.status_message = e;
+                    return;
+                }
+            };
+            let job = MergeJob {
+                args,
+                audio: self.audio_filters.clone(),
+                segments,
+                accurate_cut: self.accurate_cut,
+                delete_sources: self.delete_orig,
+            };
+
+            self.status_message = match job::run(&self.tools, &job) {
+                Ok(message) => message,
+                Err(e) => e,
+            };
+        }
+    }
+}
+
+impl eframe::App for FFmpegApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        storage.set_string("ffmpeg_path", self.tools.ffmpeg.display().to_string());
+        storage.set_string("ffprobe_path", self.tools.ffprobe.display().to_string());
+        // 保存失败时保留上次的预设文件，下次保存时重试
+        if let Some(path) = encode::profiles_path() {
+            let _ = encode::save_profiles(&path, &self.profiles);
+        }
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("FFmpeg 视频/音频合并");
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.mode, Mode::Merge, "合并");
+                ui.selectable_value(&mut self.mode, Mode::Concat, "拼接");
+                ui.selectable_value(&mut self.mode, Mode::Extract, "提取");
+                ui.selectable_value(&mut self.mode, Mode::Remux, "转封装");
+                ui.selectable_value(&mut self.mode, Mode::Transcode, "转码");
             });
+            ui.label("拖动文件到窗口，自动识别");
+            ui.add_space(10.0);
+            self.settings_ui(ui);
+            ui.add_space(10.0);
 
-            // 状态信息显示
-            if !self.status_message.is_empty() {
-                ui.label(&self.status_message);
+            // 文件拖放处理
+            let dropped: Vec<PathBuf> = ctx.input(|i| {
+                i.raw
+                    .dropped_files
+                    .iter()
+                    .filter_map(|f| f.path.clone())
+                    .collect()
+            });
+            if !dropped.is_empty() {
+                self.handle_dropped(dropped);
             }
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                match self.mode {
+                    Mode::Merge => self.merge_ui(ui),
+                    Mode::Concat => self.concat_ui(ui),
+                    Mode::Extract => self.extract_ui(ui),
+                    Mode::Remux => self.remux_ui(ui),
+                    Mode::Transcode => self.transcode_ui(ui),
+                }
+
+                // 状态信息显示
+                if !self.status_message.is_empty() {
+                    ui.label(&self.status_message);
+                }
+                ui.add_space(10.0);
+                self.log_ui(ui);
+            });
         });
     }
 }
 
 fn main() -> eframe::Result<()> {
+    // 带参数启动时进入命令行模式
+    let args: Vec<std::ffi::OsString> = std::env::args_os().skip(1).collect();
+    if !args.is_empty() {
+        cli::attach_console();
+        std::process::exit(cli::run(&args));
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([480.0, 320.0])
-            .with_title("FFmpeg 合并器"),
+            .with_inner_size([520.0, 480.0])
+            .with_title(APP_NAME),
         ..Default::default()
     };
 
     eframe::run_native(
-        "FFmpeg 合并器",
+        APP_NAME,
         native_options,
         Box::new(|cc| Ok(Box::new(FFmpegApp::new(cc)))),
     )
@@ -0,0 +1,309 @@
+use crate::tools::Tools;
+use crate::trim::WorkDir;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Chapter {
+    // 起止时间（秒）
+    pub start: f64,
+    pub end: f64,
+    pub title: String,
+}
+
+// 全局元数据与章节，对应 ffmetadata 格式
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub title: String,
+    pub artist: String,
+    pub comment: String,
+    pub chapters: Vec<Chapter>,
+}
+
+impl Metadata {
+    pub fn is_empty(&self) -> bool {
+        self.title.is_empty()
+            && self.artist.is_empty()
+            && self.comment.is_empty()
+            && self.chapters.is_empty()
+    }
+
+    // 解析 ffmetadata 文本，未识别的键会被忽略
+    pub fn parse(text: &str) -> Self {
+        let mut metadata = Metadata::default();
+        // 当前章节及其 TIMEBASE，START/END 在章节结束时换算为秒
+        let mut chapter: Option<(Chapter, f64)> = None;
+        // [STREAM] 等其他段中的键不属于全局元数据
+        let mut other_section = false;
+        let finish = |chapter: Option<(Chapter, f64)>, metadata: &mut Metadata| {
+            if let Some((mut c, timebase)) = chapter {
+                c.start *= timebase;
+                c.end *= timebase;
+                metadata.chapters.push(c);
+            }
+        };
+
+        for line in logical_lines(text) {
+            let line = line.as_str();
+            if line.starts_with(';') || line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with('[') {
+                finish(chapter.take(), &mut metadata);
+                other_section = line.trim() != "[CHAPTER]";
+                if !other_section {
+                    chapter = Some((Chapter::default(), 1.0));
+                }
+                continue;
+            }
+
+            let Some((key, value)) = split_unescaped(line) else {
+                continue;
+            };
+            match chapter.as_mut() {
+                Some((c, timebase)) => match key.to_lowercase().as_str() {
+                    "timebase" => *timebase = parse_timebase(&value).unwrap_or(1.0),
+                    "start" => c.start = value.parse().unwrap_or(0.0),
+                    "end" => c.end = value.parse().unwrap_or(0.0),
+                    "title" => c.title = value,
+                    _ => {}
+                },
+                None if other_section => {}
+                None => match key.to_lowercase().as_str() {
+                    "title" => metadata.title = value,
+                    "artist" => metadata.artist = value,
+                    "comment" => metadata.comment = value,
+                    _ => {}
+                },
+            }
+        }
+        finish(chapter.take(), &mut metadata);
+
+        metadata
+    }
+
+    pub fn to_ffmetadata(&self) -> String {
+        let mut text = String::from(";FFMETADATA1\n");
+        for (key, value) in [
+            ("title", &self.title),
+            ("artist", &self.artist),
+            ("comment", &self.comment),
+        ] {
+            if !value.is_empty() {
+                text.push_str(&format!("{}={}\n", key, escape(value)));
+            }
+        }
+        for chapter in &self.chapters {
+            text.push_str("[CHAPTER]\nTIMEBASE=1/1000\n");
+            text.push_str(&format!(
+                "START={}\n",
+                (chapter.start * 1000.0).round() as i64
+            ));
+            text.push_str(&format!("END={}\n", (chapter.end * 1000.0).round() as i64));
+            if !chapter.title.is_empty() {
+                text.push_str(&format!("title={}\n", escape(&chapter.title)));
+            }
+        }
+        text
+    }
+}
+
+// 以未转义反斜杠结尾的行与下一行相连，构成多行值
+fn logical_lines(text: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+
+    for line in text.lines() {
+        current.push_str(line);
+        let trailing = line.chars().rev().take_while(|&c| c == '\\').count();
+        if trailing % 2 == 1 {
+            current.push('\n');
+            continue;
+        }
+        lines.push(std::mem::take(&mut current));
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    lines
+}
+
+fn parse_timebase(value: &str) -> Option<f64> {
+    let (num, den) = value.split_once('/')?;
+    let num: f64 = num.trim().parse().ok()?;
+    let den: f64 = den.trim().parse().ok()?;
+    (den != 0.0).then(|| num / den)
+}
+
+// ffmetadata 中 '=', ';', '#', '\\' 和换行需要用反斜杠转义
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+// 按第一个未转义的 '=' 拆分，并还原转义字符
+fn split_unescaped(line: &str) -> Option<(String, String)> {
+    let mut key = String::new();
+    let mut value = String::new();
+    let mut in_value = false;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        let target = if in_value { &mut value } else { &mut key };
+        match c {
+            '\\' => target.extend(chars.next()),
+            '=' if !in_value => in_value = true,
+            _ => target.push(c),
+        }
+    }
+
+    in_value.then_some((key, value))
+}
+
+// 读取 ffmetadata 文件
+pub fn read_file(path: &Path) -> Result<Metadata, String> {
+    std::fs::read_to_string(path)
+        .map(|text| Metadata::parse(&text))
+        .map_err(|e| format!("无法读取元数据文件: {}", e))
+}
+
+pub fn write_file(path: &Path, metadata: &Metadata) -> Result<(), String> {
+    std::fs::write(path, metadata.to_ffmetadata()).map_err(|e| format!("无法写入元数据文件: {}", e))
+}
+
+// 从媒体文件中导出元数据与章节
+pub fn extract(tools: &Tools, input: &Path) -> Result<Metadata, String> {
+    let args: Vec<OsString> = vec![
+        "-v".into(),
+        "error".into(),
+        "-i".into(),
+        input.into(),
+        "-f".into(),
+        "ffmetadata".into(),
+        "-".into(),
+    ];
+    let output = tools.execute_ffmpeg(&args, "读取元数据")?;
+
+    Ok(Metadata::parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// 合并时传给 ffmpeg 的临时 ffmetadata 文件，离开作用域时连同临时目录一起删除
+pub struct MetadataFile {
+    path: PathBuf,
+    _dir: WorkDir,
+}
+
+impl MetadataFile {
+    pub fn create(metadata: &Metadata) -> Result<Self, String> {
+        let dir = WorkDir::create()?;
+        let path = dir.join("metadata.ffmeta");
+        write_file(&path, metadata)?;
+        Ok(Self { path, _dir: dir })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{self, Reply, StubRunner};
+
+    const SPECIAL: &str = "a=b; c#d \\e\nline2\n";
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(escape("a=b;c#d\\e\nf"), "a\\=b\\;c\\#d\\\\e\\\nf");
+        assert_eq!(escape("plain 文本"), "plain 文本");
+    }
+
+    #[test]
+    fn round_trips_special_characters() {
+        let metadata = Metadata {
+            title: SPECIAL.into(),
+            artist: "\\".into(),
+            comment: "#;=".into(),
+            chapters: vec![
+                Chapter {
+                    start: 0.0,
+                    end: 61.5,
+                    title: SPECIAL.into(),
+                },
+                Chapter {
+                    start: 61.5,
+                    end: 120.0,
+                    title: "end\\".into(),
+                },
+            ],
+        };
+
+        let text = metadata.to_ffmetadata();
+        assert_eq!(Metadata::parse(&text), metadata, "{}", text);
+    }
+
+    #[test]
+    fn extracts_metadata_with_ffmpeg() {
+        let runner =
+            StubRunner::new(|_, _| Reply::ok(";FFMETADATA1\ntitle=Song\\;1\nartist=Band\n"));
+
+        let metadata = extract(&runner.tools(), Path::new("in.mp4")).unwrap();
+        assert_eq!(metadata.title, "Song;1");
+        assert_eq!(metadata.artist, "Band");
+        assert_eq!(
+            stub::calls_to(&runner, "ffmpeg"),
+            [["-v", "error", "-i", "in.mp4", "-f", "ffmetadata", "-"]]
+        );
+    }
+
+    #[test]
+    fn metadata_file_is_removed_on_drop() {
+        let metadata = Metadata {
+            title: "t".into(),
+            ..Default::default()
+        };
+        let file = MetadataFile::create(&metadata).unwrap();
+        let path = file.path().to_path_buf();
+        assert_eq!(read_file(&path).unwrap(), metadata);
+        // 同一进程内连续创建的文件互不冲突
+        let other = MetadataFile::create(&Metadata::default()).unwrap();
+        assert_ne!(other.path(), path);
+        drop(file);
+        assert!(!path.exists());
+        assert!(other.path().exists());
+    }
+
+    #[test]
+    fn parses_ffmpeg_output() {
+        let text = ";FFMETADATA1\n\
+            title=Multi\\\nline\n\
+            encoder=Lavf60.16.100\n\
+            # comment\n\
+            [STREAM]\n\
+            title=stream title\n\
+            [CHAPTER]\n\
+            TIMEBASE=1/1000\n\
+            START=0\n\
+            END=1500\n\
+            title=Intro\\=1\n";
+
+        let metadata = Metadata::parse(text);
+        assert_eq!(metadata.title, "Multi\nline");
+        assert_eq!(
+            metadata.chapters,
+            [Chapter {
+                start: 0.0,
+                end: 1.5,
+                title: "Intro=1".into(),
+            }]
+        );
+    }
+}
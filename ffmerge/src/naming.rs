@@ -0,0 +1,204 @@
+use crate::ffmpeg::Container;
+use crate::probe;
+use crate::tools::Tools;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_TEMPLATE: &str = "{stem}.merged";
+
+pub const TEMPLATE_HELP: &str =
+    "可用变量: {stem} 视频文件名, {date} 日期, {resolution} 分辨率, {vcodec} 视频编码, {acodec} 音频编码";
+
+// 输出文件已存在时的处理方式
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Collision {
+    #[default]
+    Suffix,
+    Overwrite,
+    Skip,
+}
+
+impl Collision {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "suffix" => Some(Collision::Suffix),
+            "overwrite" => Some(Collision::Overwrite),
+            "skip" => Some(Collision::Skip),
+            _ => None,
+        }
+    }
+}
+
+// 根据模板生成输出路径，输出到视频所在目录
+pub fn output_path(
+    tools: &Tools,
+    template: &str,
+    video: &Path,
+    audio: Option<&Path>,
+    container: Container,
+) -> PathBuf {
+    let stem = video
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "output".to_string());
+
+    let mut name = template
+        .replace("{stem}", &stem)
+        .replace("{date}", &chrono::Local::now().format("%Y%m%d").to_string());
+
+    // 只在模板需要时才调用 ffprobe
+    if name.contains("{resolution}") || name.contains("{vcodec}") {
+        let info = probe::probe(tools, video).unwrap_or_default();
+        let resolution = info
+            .resolution()
+            .map(|(w, h)| format!("{}x{}", w, h))
+            .unwrap_or_default();
+        let vcodec = info
+            .first_stream("video")
+            .map(|s| s.codec_name.clone())
+            .unwrap_or_default();
+        name = name
+            .replace("{resolution}", &resolution)
+            .replace("{vcodec}", &vcodec);
+    }
+    if name.contains("{acodec}") {
+        let acodec = audio
+            .and_then(|audio| probe::probe(tools, audio))
+            .and_then(|info| info.first_stream("audio").map(|s| s.codec_name.clone()))
+            .unwrap_or_default();
+        name = name.replace("{acodec}", &acodec);
+    }
+
+    if name.trim().is_empty() {
+        name = stem;
+    }
+    let name = format!("{}.{}", name, container.extension());
+
+    match video.parent() {
+        Some(parent) => parent.join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+// 在输入文件旁生成 "<stem>.<tag>.<ext>" 形式的路径
+pub fn derived_path(input: &Path, tag: &str, ext: &str) -> PathBuf {
+    let stem = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "output".to_string());
+    input.with_file_name(format!("{}.{}.{}", stem, tag, ext))
+}
+
+// 处理同名文件，返回 None 表示跳过
+pub fn resolve_collision(path: PathBuf, collision: Collision) -> Option<PathBuf> {
+    if !path.exists() {
+        return Some(path);
+    }
+
+    match collision {
+        Collision::Overwrite => Some(path),
+        Collision::Skip => None,
+        Collision::Suffix => {
+            let stem = path.file_stem()?.to_string_lossy().to_string();
+            let ext = path
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            (1..)
+                .map(|i| path.with_file_name(format!("{}-{}{}", stem, i, ext)))
+                .find(|candidate| !candidate.exists())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{self, Reply, StubRunner};
+    use crate::trim::WorkDir;
+
+    #[test]
+    fn default_template_uses_video_stem() {
+        let tools = Tools::default();
+        let path = output_path(
+            &tools,
+            DEFAULT_TEMPLATE,
+            Path::new("/videos/clip.mkv"),
+            None,
+            Container::Mp4,
+        );
+        assert_eq!(path, PathBuf::from("/videos/clip.merged.mp4"));
+    }
+
+    #[test]
+    fn template_probes_only_when_needed() {
+        let runner = StubRunner::new(|_, args| {
+            let path = args.last().unwrap().to_string_lossy().to_string();
+            let streams: &[(&str, &str)] = if path.ends_with(".m4a") {
+                &[("audio", "aac")]
+            } else {
+                &[("video", "hevc"), ("audio", "opus")]
+            };
+            Reply::ok(&stub::probe_output(10.0, streams))
+        });
+        let tools = runner.tools();
+
+        let path = output_path(
+            &tools,
+            "{stem}-{resolution}-{vcodec}-{acodec}",
+            Path::new("dir/clip.mp4"),
+            Some(Path::new("dir/voice.m4a")),
+            Container::Mkv,
+        );
+        assert_eq!(path, PathBuf::from("dir/clip-1920x1080-hevc-aac.mkv"));
+        assert_eq!(runner.calls().len(), 2);
+
+        output_path(
+            &tools,
+            "{stem}",
+            Path::new("clip.mp4"),
+            None,
+            Container::Mp4,
+        );
+        assert_eq!(runner.calls().len(), 2);
+    }
+
+    #[test]
+    fn empty_template_falls_back_to_stem() {
+        let path = output_path(
+            &Tools::default(),
+            " ",
+            Path::new("clip.mp4"),
+            None,
+            Container::Mov,
+        );
+        assert_eq!(path, PathBuf::from("clip.mov"));
+    }
+
+    #[test]
+    fn derived_path_keeps_directory() {
+        assert_eq!(
+            derived_path(Path::new("/a/movie.mkv"), "a1", "m4a"),
+            PathBuf::from("/a/movie.a1.m4a")
+        );
+    }
+
+    #[test]
+    fn collision_handling() {
+        let dir = WorkDir::create().unwrap();
+        let taken = dir.join("out.mp4");
+        std::fs::write(&taken, "").unwrap();
+        std::fs::write(dir.join("out-1.mp4"), "").unwrap();
+
+        let free = dir.join("free.mp4");
+        assert_eq!(resolve_collision(free.clone(), Collision::Skip), Some(free));
+        assert_eq!(
+            resolve_collision(taken.clone(), Collision::Overwrite),
+            Some(taken.clone())
+        );
+        assert_eq!(resolve_collision(taken.clone(), Collision::Skip), None);
+        assert_eq!(
+            resolve_collision(taken, Collision::Suffix),
+            Some(dir.join("out-2.mp4"))
+        );
+    }
+}
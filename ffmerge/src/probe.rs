@@ -0,0 +1,187 @@
+use crate::tools::Tools;
+use std::ffi::OsString;
+use std::path::Path;
+
+// 时长差异超过该值（秒）时给出警告
+pub const DURATION_TOLERANCE: f64 = 1.0;
+
+#[derive(Clone, Debug, Default)]
+pub struct StreamInfo {
+    pub index: u32,
+    pub codec_type: String,
+    pub codec_name: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub pix_fmt: String,
+    // 形如 "30000/1001"
+    pub frame_rate: String,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub language: String,
+    // 编码档次与级别，如 "High" 与 40，精确剪切时用于匹配重新编码的参数
+    pub profile: String,
+    pub level: Option<i32>,
+    // 形如 "1/15360"
+    pub time_base: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MediaInfo {
+    pub duration: Option<f64>,
+    pub streams: Vec<StreamInfo>,
+}
+
+impl StreamInfo {
+    // 用于列表显示的简要描述
+    pub fn describe(&self) -> String {
+        let mut text = format!("#{} {} {}", self.index, self.codec_type, self.codec_name);
+        if let (Some(w), Some(h)) = (self.width, self.height) {
+            text.push_str(&format!(" {}x{}", w, h));
+        }
+        if let Some(channels) = self.channels {
+            text.push_str(&format!(" {}ch", channels));
+        }
+        if !self.language.is_empty() {
+            text.push_str(&format!(" [{}]", self.language));
+        }
+        text
+    }
+}
+
+impl MediaInfo {
+    pub fn first_stream(&self, codec_type: &str) -> Option<&StreamInfo> {
+        self.streams.iter().find(|s| s.codec_type == codec_type)
+    }
+
+    pub fn resolution(&self) -> Option<(u32, u32)> {
+        let video = self.first_stream("video")?;
+        Some((video.width?, video.height?))
+    }
+}
+
+// 通过 ffprobe 获取容器时长与各流信息
+pub fn probe(tools: &Tools, path: &Path) -> Option<MediaInfo> {
+    let args: Vec<OsString> = vec![
+        "-v".into(),
+        "error".into(),
+        "-show_entries".into(),
+        "format=duration:stream=index,codec_type,codec_name,width,height,pix_fmt,r_frame_rate,sample_rate,channels,profile,level,time_base:stream_tags=language".into(),
+        "-of".into(),
+        "default".into(),
+        path.into(),
+    ];
+    let output = tools.run_ffprobe(&args).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// 解析 ffprobe `-of default` 输出的 [STREAM]/[FORMAT] 段
+pub fn parse(text: &str) -> MediaInfo {
+    let mut info = MediaInfo::default();
+    let mut stream: Option<StreamInfo> = None;
+
+    for line in text.lines().map(str::trim) {
+        match line {
+            "[STREAM]" => stream = Some(StreamInfo::default()),
+            "[/STREAM]" => info.streams.extend(stream.take()),
+            _ => {
+                let Some((key, value)) = line.split_once('=') else {
+                    continue;
+                };
+                match (stream.as_mut(), key) {
+                    (Some(s), "index") => s.index = value.parse().unwrap_or_default(),
+                    (Some(s), "codec_type") => s.codec_type = value.to_string(),
+                    (Some(s), "codec_name") => s.codec_name = value.to_string(),
+                    (Some(s), "width") => s.width = value.parse().ok(),
+                    (Some(s), "height") => s.height = value.parse().ok(),
+                    (Some(s), "pix_fmt") => s.pix_fmt = value.to_string(),
+                    (Some(s), "r_frame_rate") => s.frame_rate = value.to_string(),
+                    (Some(s), "sample_rate") => s.sample_rate = value.parse().ok(),
+                    (Some(s), "channels") => s.channels = value.parse().ok(),
+                    (Some(s), "TAG:language") => s.language = value.to_string(),
+                    (Some(s), "profile") => s.profile = value.to_string(),
+                    (Some(s), "level") => s.level = value.parse().ok().filter(|l| *l > 0),
+                    (Some(s), "time_base") => s.time_base = value.to_string(),
+                    (None, "duration") => info.duration = value.parse().ok(),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    info
+}
+
+// 获取媒体时长（秒）
+pub fn duration(tools: &Tools, path: &Path) -> Option<f64> {
+    probe(tools, path)?.duration
+}
+
+// 计算考虑音频延迟后的时长差（视频 - 音频），差异在容差内时返回 None
+pub fn duration_mismatch(video: f64, audio: f64, audio_delay_ms: i64) -> Option<f64> {
+    let diff = video - (audio + audio_delay_ms as f64 / 1000.0);
+    (diff.abs() > DURATION_TOLERANCE).then_some(diff)
+}
+
+pub fn mismatch_warning(diff: f64) -> String {
+    if diff > 0.0 {
+        format!("⚠ 音频比视频短 {:.1} 秒", diff)
+    } else {
+        format!("⚠ 音频比视频长 {:.1} 秒", -diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{self, Reply, StubRunner};
+
+    #[test]
+    fn parses_streams_and_duration() {
+        let text =
+            "[STREAM]\nindex=0\ncodec_type=video\ncodec_name=hevc\nwidth=3840\nheight=2160\n\
+            pix_fmt=yuv420p10le\nr_frame_rate=24000/1001\nprofile=Main 10\nlevel=150\n\
+            time_base=1/24000\n[/STREAM]\n\
+            [STREAM]\nindex=1\ncodec_type=audio\ncodec_name=opus\nsample_rate=48000\nchannels=6\n\
+            profile=unknown\nlevel=-99\nTAG:language=jpn\n[/STREAM]\n\
+            [FORMAT]\nduration=N/A\n[/FORMAT]\n";
+
+        let info = parse(text);
+        assert_eq!(info.duration, None);
+        assert_eq!(info.resolution(), Some((3840, 2160)));
+        assert_eq!(info.streams[0].frame_rate, "24000/1001");
+        assert_eq!(info.streams[0].profile, "Main 10");
+        assert_eq!(info.streams[0].level, Some(150));
+        assert_eq!(info.streams[0].time_base, "1/24000");
+        assert_eq!(info.streams[1].level, None);
+        assert_eq!(
+            info.first_stream("audio").unwrap().describe(),
+            "#1 audio opus 6ch [jpn]"
+        );
+        assert!(info.first_stream("subtitle").is_none());
+    }
+
+    #[test]
+    fn probe_fails_quietly() {
+        let runner = StubRunner::new(|_, _| Reply::fail(1, "No such file"));
+        assert!(probe(&runner.tools(), Path::new("missing.mp4")).is_none());
+
+        let runner = StubRunner::new(|_, _| Reply::ok(&stub::probe_output(12.5, &[])));
+        assert_eq!(duration(&runner.tools(), Path::new("a.mp4")), Some(12.5));
+        let calls = stub::calls_to(&runner, "ffprobe");
+        assert_eq!(calls[0].last().unwrap(), "a.mp4");
+    }
+
+    #[test]
+    fn mismatch_accounts_for_audio_delay() {
+        assert_eq!(duration_mismatch(10.0, 10.5, 0), None);
+        assert_eq!(duration_mismatch(10.0, 8.0, 0), Some(2.0));
+        assert_eq!(duration_mismatch(10.0, 8.0, 1500), None);
+        assert_eq!(duration_mismatch(10.0, 10.0, 2000), Some(-2.0));
+        assert_eq!(mismatch_warning(2.0), "⚠ 音频比视频短 2.0 秒");
+        assert_eq!(mismatch_warning(-1.25), "⚠ 音频比视频长 1.2 秒");
+    }
+}
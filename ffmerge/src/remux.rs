@@ -0,0 +1,303 @@
+use crate::ffmpeg::Container;
+use crate::naming::{self, Collision};
+use crate::probe::StreamInfo;
+use crate::tools::Tools;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+// 不重新编码的转封装参数构建器
+#[derive(Clone, Debug)]
+pub struct RemuxArgs {
+    input: PathBuf,
+    output: PathBuf,
+    container: Container,
+    faststart: bool,
+    strip_data: bool,
+    strip_subtitles: bool,
+    overwrite: bool,
+    // 探测到的输入流，用于判断字幕能否转为 mov_text
+    streams: Option<Vec<StreamInfo>>,
+}
+
+// 可以转换为 mp4 mov_text 的文本字幕格式，PGS、VobSub 等图像字幕无法转换
+const TEXT_SUBTITLES: &[&str] = &["subrip", "srt", "ass", "ssa", "webvtt", "mov_text", "text"];
+
+impl RemuxArgs {
+    pub fn new(
+        input: impl Into<PathBuf>,
+        output: impl Into<PathBuf>,
+        container: Container,
+    ) -> Self {
+        Self {
+            input: input.into(),
+            output: output.into(),
+            container,
+            faststart: false,
+            strip_data: true,
+            strip_subtitles: false,
+            overwrite: false,
+            streams: None,
+        }
+    }
+
+    // 将 moov 移到文件头，便于网页边下边播，仅对 mp4/mov 有效
+    pub fn faststart(mut self, faststart: bool) -> Self {
+        self.faststart = faststart;
+        self
+    }
+
+    // 去除数据流（如 TS 中的 timed_id3、录屏软件写入的 tmcd）
+    pub fn strip_data(mut self, strip: bool) -> Self {
+        self.strip_data = strip;
+        self
+    }
+
+    pub fn strip_subtitles(mut self, strip: bool) -> Self {
+        self.strip_subtitles = strip;
+        self
+    }
+
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    pub fn streams(mut self, streams: Option<Vec<StreamInfo>>) -> Self {
+        self.streams = streams;
+        self
+    }
+
+    pub fn output(&self) -> &Path {
+        &self.output
+    }
+
+    fn is_mp4_family(&self) -> bool {
+        matches!(self.container, Container::Mp4 | Container::Mov)
+    }
+
+    // 输出为 mp4/mov 时需要去除的图像字幕
+    fn bitmap_subtitles(&self) -> impl Iterator<Item = &StreamInfo> {
+        let keep = self.is_mp4_family() && !self.strip_subtitles;
+        self.streams
+            .iter()
+            .flatten()
+            .filter(move |s| keep && s.codec_type == "subtitle")
+            .filter(|s| !TEXT_SUBTITLES.contains(&s.codec_name.as_str()))
+    }
+
+    // 需要提示用户的被去除的流
+    pub fn warnings(&self) -> Vec<String> {
+        self.bitmap_subtitles()
+            .map(|s| {
+                format!(
+                    "⚠ 字幕流 #{} ({}) 无法转为 {} 支持的文本字幕，已去除",
+                    s.index,
+                    s.codec_name,
+                    self.container.extension()
+                )
+            })
+            .collect()
+    }
+
+    pub fn build(&self) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![
+            if self.overwrite { "-y" } else { "-n" }.into(),
+            "-i".into(),
+            self.input.clone().into(),
+            "-map".into(),
+            "0".into(),
+        ];
+        if self.strip_data {
+            args.extend(["-map".into(), "-0:d?".into()]);
+        }
+        if self.strip_subtitles {
+            args.extend(["-map".into(), "-0:s?".into()]);
+        }
+        let mp4_family = self.is_mp4_family();
+        if mp4_family {
+            // mp4 不能容纳 mkv 中的附件（如字体）
+            args.extend(["-map".into(), "-0:t?".into()]);
+        }
+        for subtitle in self.bitmap_subtitles() {
+            args.extend(["-map".into(), format!("-0:{}", subtitle.index).into()]);
+        }
+        args.extend(["-c".into(), "copy".into()]);
+
+        // mp4 只支持 mov_text 文本字幕；未探测时假定字幕均为文本
+        let text_subtitles = match &self.streams {
+            Some(streams) => streams.iter().any(|s| {
+                s.codec_type == "subtitle" && TEXT_SUBTITLES.contains(&s.codec_name.as_str())
+            }),
+            None => true,
+        };
+        if mp4_family && !self.strip_subtitles && text_subtitles {
+            args.extend(["-c:s".into(), "mov_text".into()]);
+        }
+        if mp4_family && self.faststart {
+            args.extend(["-movflags".into(), "+faststart".into()]);
+        }
+        args.extend([
+            "-f".into(),
+            self.container.muxer().into(),
+            self.output.clone().into(),
+        ]);
+        args
+    }
+}
+
+// 默认输出到输入旁，仅替换扩展名；与输入同名时追加 .remux
+pub fn default_output(input: &Path, container: Container) -> PathBuf {
+    let output = input.with_extension(container.extension());
+    if output == input {
+        naming::derived_path(input, "remux", container.extension())
+    } else {
+        output
+    }
+}
+
+pub fn output_for(input: &Path, container: Container, collision: Collision) -> Option<PathBuf> {
+    naming::resolve_collision(default_output(input, container), collision)
+}
+
+pub fn remux(tools: &Tools, args: &RemuxArgs) -> Result<PathBuf, String> {
+    tools.execute_ffmpeg(&args.build(), "转封装")?;
+    Ok(args.output().to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::probe;
+    use crate::stub::{self, strings, Reply, StubRunner};
+
+    // 视频、音频、SRT 字幕、PGS 字幕和字体附件
+    fn mkv_streams() -> Option<Vec<StreamInfo>> {
+        let text = stub::probe_output(
+            10.0,
+            &[
+                ("video", "h264"),
+                ("audio", "aac"),
+                ("subtitle", "subrip"),
+                ("subtitle", "hdmv_pgs_subtitle"),
+                ("attachment", "ttf"),
+            ],
+        );
+        Some(probe::parse(&text).streams)
+    }
+
+    #[test]
+    fn builds_mkv_remux_keeping_everything_but_data() {
+        let args = RemuxArgs::new("in.mp4", "out.mkv", Container::Mkv)
+            .streams(mkv_streams())
+            .build();
+        assert_eq!(
+            strings(&args),
+            [
+                "-n", "-i", "in.mp4", "-map", "0", "-map", "-0:d?", "-c", "copy", "-f", "matroska",
+                "out.mkv"
+            ]
+        );
+    }
+
+    #[test]
+    fn mp4_drops_attachments_and_bitmap_subtitles() {
+        let args = RemuxArgs::new("in.mkv", "out.mp4", Container::Mp4)
+            .faststart(true)
+            .streams(mkv_streams());
+        assert_eq!(
+            strings(&args.build()),
+            [
+                "-n",
+                "-i",
+                "in.mkv",
+                "-map",
+                "0",
+                "-map",
+                "-0:d?",
+                "-map",
+                "-0:t?",
+                "-map",
+                "-0:3",
+                "-c",
+                "copy",
+                "-c:s",
+                "mov_text",
+                "-movflags",
+                "+faststart",
+                "-f",
+                "mp4",
+                "out.mp4"
+            ]
+        );
+        let warnings = args.warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].contains("#3 (hdmv_pgs_subtitle)"),
+            "{}",
+            warnings[0]
+        );
+    }
+
+    #[test]
+    fn bitmap_only_subtitles_are_not_converted() {
+        let text = stub::probe_output(10.0, &[("video", "h264"), ("subtitle", "dvd_subtitle")]);
+        let args = RemuxArgs::new("in.mkv", "out.mov", Container::Mov)
+            .strip_data(false)
+            .streams(Some(probe::parse(&text).streams))
+            .build();
+        let args = strings(&args);
+        assert!(args.windows(2).any(|w| w == ["-map", "-0:1"]), "{:?}", args);
+        assert!(!args.contains(&"mov_text".to_string()), "{:?}", args);
+    }
+
+    #[test]
+    fn stripped_subtitles_need_no_conversion() {
+        let args = RemuxArgs::new("in.mkv", "out.mp4", Container::Mp4)
+            .strip_subtitles(true)
+            .streams(mkv_streams());
+        assert!(args.warnings().is_empty());
+        let built = strings(&args.build());
+        assert!(built.windows(2).any(|w| w == ["-map", "-0:s?"]));
+        assert!(!built.contains(&"mov_text".to_string()));
+        assert!(!built.contains(&"-0:3".to_string()));
+    }
+
+    #[test]
+    fn unprobed_input_assumes_text_subtitles() {
+        let args = strings(&RemuxArgs::new("in.mkv", "out.mp4", Container::Mp4).build());
+        assert!(args.windows(2).any(|w| w == ["-c:s", "mov_text"]));
+        assert!(args.windows(2).any(|w| w == ["-map", "-0:t?"]));
+    }
+
+    #[test]
+    fn default_output_avoids_input() {
+        assert_eq!(
+            default_output(Path::new("dir/a.mkv"), Container::Mp4),
+            PathBuf::from("dir/a.mp4")
+        );
+        assert_eq!(
+            default_output(Path::new("dir/a.mp4"), Container::Mp4),
+            PathBuf::from("dir/a.remux.mp4")
+        );
+    }
+
+    #[test]
+    fn remux_runs_built_args() {
+        let runner = StubRunner::new(|_, _| Reply::default());
+        let args = RemuxArgs::new("in.mkv", "out.mkv", Container::Mkv).overwrite(true);
+
+        let output = remux(&runner.tools(), &args).unwrap();
+        assert_eq!(output, PathBuf::from("out.mkv"));
+        assert_eq!(stub::calls_to(&runner, "ffmpeg"), [strings(&args.build())]);
+    }
+
+    #[test]
+    fn remux_reports_ffmpeg_failure() {
+        let runner = StubRunner::new(|_, _| Reply::fail(1, "Invalid data found"));
+        let args = RemuxArgs::new("in.mkv", "out.mp4", Container::Mp4);
+
+        let error = remux(&runner.tools(), &args).unwrap_err();
+        assert!(error.starts_with("转封装失败（退出码 1）"), "{}", error);
+        assert!(error.contains("Invalid data found"), "{}", error);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::log::{LogLine, Source};
+use chrono::Local;
+use std::ffi::OsString;
+use std::io::{BufRead, BufReader, Read};
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+// 一次调用的结果，lines 为按到达时间排列的 stdout/stderr 各行
+pub struct Run {
+    pub output: Output,
+    pub lines: Vec<LogLine>,
+}
+
+// 外部程序的调用方式，测试中可替换为桩实现
+pub trait Runner: std::fmt::Debug + Send + Sync {
+    fn run(&self, program: &Path, args: &[OsString]) -> std::io::Result<Run>;
+
+    // 将文件移至系统回收站
+    fn trash(&self, path: &Path) -> Result<(), String> {
+        trash::delete(path).map_err(|e| e.to_string())
+    }
+}
+
+// 启动子进程，同时读取 stdout/stderr 并记录每一行的时间
+#[derive(Debug, Default)]
+pub struct ProcessRunner;
+
+impl Runner for ProcessRunner {
+    fn run(&self, program: &Path, args: &[OsString]) -> std::io::Result<Run> {
+        let mut cmd = Command::new(program);
+        cmd.args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        #[cfg(windows)]
+        cmd.creation_flags(134_217_728u32);
+
+        let mut child = cmd.spawn()?;
+        let stdout = child.stdout.take().expect("stdout 已设置为管道");
+        let stderr = child.stderr.take().expect("stderr 已设置为管道");
+        let ((stdout, mut lines), (stderr, err_lines)) = std::thread::scope(|s| {
+            let err = s.spawn(|| read_lines(stderr, Source::Stderr));
+            let out = read_lines(stdout, Source::Stdout);
+            (out, err.join().unwrap_or_default())
+        });
+        let status = child.wait()?;
+
+        lines.extend(err_lines);
+        lines.sort_by_key(|l| l.time);
+        Ok(Run {
+            output: Output {
+                status,
+                stdout,
+                stderr,
+            },
+            lines,
+        })
+    }
+}
+
+// 逐行读取输出并记录时间，'\r' 分隔的进度行只保留最后一段，连续的二进制数据合并为一行
+fn read_lines(reader: impl Read, source: Source) -> (Vec<u8>, Vec<LogLine>) {
+    let mut reader = BufReader::new(reader);
+    let mut data = Vec::new();
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+    let mut binary = 0;
+
+    while reader.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+        data.extend_from_slice(&line);
+        let text = match std::str::from_utf8(&line) {
+            Ok(text) => {
+                binary = 0;
+                text.trim_end_matches(['\r', '\n'])
+                    .rsplit('\r')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            }
+            Err(_) => {
+                if binary > 0 {
+                    lines.pop();
+                }
+                binary += line.len();
+                format!("<{} 字节二进制数据>", binary)
+            }
+        };
+        lines.push(LogLine {
+            time: Local::now(),
+            source,
+            text,
+        });
+        line.clear();
+    }
+
+    (data, lines)
+}
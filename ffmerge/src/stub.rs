@@ -0,0 +1,298 @@
+// 测试用的桩 ffmpeg/ffprobe：脚本形式的桩程序与不启动进程的桩运行器
+use crate::log::{LogLine, Source};
+use crate::runner::{Run, Runner};
+use crate::tools::Tools;
+use crate::trim::WorkDir;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output};
+use std::sync::{Arc, Mutex};
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(code << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+// 桩运行器的一次应答
+#[derive(Clone, Debug, Default)]
+pub struct Reply {
+    pub code: i32,
+    // 可以是 PNG 等二进制数据
+    pub stdout: Vec<u8>,
+    pub stderr: String,
+}
+
+impl Reply {
+    pub fn ok(stdout: &str) -> Self {
+        Self::bytes(stdout.as_bytes().to_vec())
+    }
+
+    pub fn bytes(stdout: Vec<u8>) -> Self {
+        Self {
+            stdout,
+            ..Default::default()
+        }
+    }
+
+    pub fn fail(code: i32, stderr: &str) -> Self {
+        Self {
+            code,
+            stderr: stderr.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+type Respond = dyn Fn(&Path, &[OsString]) -> Reply + Send + Sync;
+
+// 记录每次调用并按 respond 返回结果，不启动任何进程，也不会真正移动文件到回收站
+pub struct StubRunner {
+    respond: Box<Respond>,
+    calls: Mutex<Vec<(PathBuf, Vec<OsString>)>>,
+    trashed: Mutex<Vec<PathBuf>>,
+    trash_error: Option<String>,
+}
+
+impl std::fmt::Debug for StubRunner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StubRunner")
+            .field("calls", &self.calls)
+            .field("trashed", &self.trashed)
+            .finish()
+    }
+}
+
+impl StubRunner {
+    pub fn new(respond: impl Fn(&Path, &[OsString]) -> Reply + Send + Sync + 'static) -> Arc<Self> {
+        Arc::new(Self {
+            respond: Box::new(respond),
+            calls: Mutex::new(Vec::new()),
+            trashed: Mutex::new(Vec::new()),
+            trash_error: None,
+        })
+    }
+
+    // 移至回收站时返回该错误
+    pub fn trash_error(self: Arc<Self>, error: &str) -> Arc<Self> {
+        let mut runner = Arc::into_inner(self).expect("桩运行器尚未共享");
+        runner.trash_error = Some(error.to_string());
+        Arc::new(runner)
+    }
+
+    pub fn calls(&self) -> Vec<(PathBuf, Vec<OsString>)> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    // 请求移至回收站的文件
+    pub fn trashed(&self) -> Vec<PathBuf> {
+        self.trashed.lock().unwrap().clone()
+    }
+
+    // 返回使用该运行器的 Tools
+    pub fn tools(self: &Arc<Self>) -> Tools {
+        Tools {
+            runner: self.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+impl Runner for StubRunner {
+    fn run(&self, program: &Path, args: &[OsString]) -> std::io::Result<Run> {
+        self.calls
+            .lock()
+            .unwrap()
+            .push((program.to_path_buf(), args.to_vec()));
+        let reply = (self.respond)(program, args);
+
+        let now = chrono::Local::now();
+        let stdout = String::from_utf8_lossy(&reply.stdout);
+        let lines = [
+            (Source::Stdout, stdout.as_ref()),
+            (Source::Stderr, reply.stderr.as_str()),
+        ]
+        .into_iter()
+        .flat_map(|(source, text)| {
+            text.lines().map(move |line| LogLine {
+                time: now,
+                source,
+                text: line.to_string(),
+            })
+        })
+        .collect();
+        Ok(Run {
+            output: Output {
+                status: exit_status(reply.code),
+                stdout: reply.stdout,
+                stderr: reply.stderr.into_bytes(),
+            },
+            lines,
+        })
+    }
+
+    fn trash(&self, path: &Path) -> Result<(), String> {
+        if let Some(error) = &self.trash_error {
+            return Err(error.clone());
+        }
+        self.trashed.lock().unwrap().push(path.to_path_buf());
+        Ok(())
+    }
+}
+
+// 便于断言的参数文本
+pub fn strings(args: &[OsString]) -> Vec<String> {
+    args.iter()
+        .map(|a| a.to_string_lossy().to_string())
+        .collect()
+}
+
+// 按程序名记录的调用参数，用于只关心 ffmpeg 或 ffprobe 调用的断言
+pub fn calls_to(runner: &StubRunner, program: &str) -> Vec<Vec<String>> {
+    runner
+        .calls()
+        .iter()
+        .filter(|(path, _)| path.ends_with(program))
+        .map(|(_, args)| strings(args))
+        .collect()
+}
+
+// ffprobe `-of default` 格式的探测结果
+pub fn probe_output(duration: f64, streams: &[(&str, &str)]) -> String {
+    let mut text = String::new();
+    for (i, (codec_type, codec_name)) in streams.iter().enumerate() {
+        text.push_str(&format!(
+            "[STREAM]\nindex={}\ncodec_type={}\ncodec_name={}\n",
+            i, codec_type, codec_name
+        ));
+        if *codec_type == "video" {
+            text.push_str(
+                "width=1920\nheight=1080\npix_fmt=yuv420p\nr_frame_rate=30/1\n\
+                 profile=High\nlevel=40\ntime_base=1/15360\n",
+            );
+        }
+        text.push_str("[/STREAM]\n");
+    }
+    text.push_str(&format!("[FORMAT]\nduration={}\n[/FORMAT]\n", duration));
+    text
+}
+
+// 写入临时目录的 sh 桩程序：记录参数、输出进度与 stderr、按需创建输出文件并以指定退出码结束
+#[cfg(unix)]
+pub struct StubScript {
+    dir: WorkDir,
+    version: String,
+    stdout: String,
+    stderr: Vec<String>,
+    progress: bool,
+    create_output: bool,
+    code: i32,
+}
+
+#[cfg(unix)]
+impl StubScript {
+    pub fn new() -> Self {
+        Self {
+            dir: WorkDir::create().unwrap(),
+            version: "6.1".into(),
+            stdout: String::new(),
+            stderr: Vec::new(),
+            progress: false,
+            create_output: false,
+            code: 0,
+        }
+    }
+
+    pub fn version(mut self, version: &str) -> Self {
+        self.version = version.to_string();
+        self
+    }
+
+    pub fn stdout(mut self, text: &str) -> Self {
+        self.stdout = text.to_string();
+        self
+    }
+
+    pub fn stderr(mut self, line: &str) -> Self {
+        self.stderr.push(line.to_string());
+        self
+    }
+
+    // 以 '\r' 分隔输出若干进度行
+    pub fn progress(mut self) -> Self {
+        self.progress = true;
+        self
+    }
+
+    // 把最后一个参数视为输出文件并写入内容
+    pub fn create_output(mut self) -> Self {
+        self.create_output = true;
+        self
+    }
+
+    pub fn exit_code(mut self, code: i32) -> Self {
+        self.code = code;
+        self
+    }
+
+    // 临时目录中的路径，与桩程序一同删除
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    // 写入脚本，返回其路径
+    pub fn install(&self, name: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let quote = |text: &str| format!("'{}'", text.replace('\'', "'\\''"));
+        let calls = self.path(&format!("{}.calls", name));
+        let mut script = format!(
+            "#!/bin/sh\nprintf '%s\\n' \"$@\" -- >> {}\n",
+            quote(&calls.display().to_string())
+        );
+        script.push_str(&format!(
+            "if [ \"$1\" = \"-version\" ]; then echo \"{} version {}\"; exit 0; fi\n",
+            name, self.version
+        ));
+        if !self.stdout.is_empty() {
+            script.push_str(&format!("printf '%s' {}\n", quote(&self.stdout)));
+        }
+        if self.progress {
+            script.push_str("printf 'frame=1 time=00:00:01\\rframe=2 time=00:00:02\\rframe=3 time=00:00:03\\n' >&2\n");
+        }
+        for line in &self.stderr {
+            script.push_str(&format!("echo {} >&2\n", quote(line)));
+        }
+        if self.create_output {
+            script.push_str("for last; do :; done\nprintf 'stub' > \"$last\"\n");
+        }
+        script.push_str(&format!("exit {}\n", self.code));
+
+        let path = self.path(name);
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    // 桩程序被调用时收到的参数，每次调用一组
+    pub fn calls(&self, name: &str) -> Vec<Vec<String>> {
+        let text =
+            std::fs::read_to_string(self.path(&format!("{}.calls", name))).unwrap_or_default();
+        let mut calls = Vec::new();
+        let mut current = Vec::new();
+        for line in text.lines() {
+            if line == "--" {
+                calls.push(std::mem::take(&mut current));
+            } else {
+                current.push(line.to_string());
+            }
+        }
+        calls
+    }
+}
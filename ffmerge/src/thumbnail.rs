@@ -0,0 +1,145 @@
+use crate::tools::Tools;
+use std::ffi::OsString;
+use std::path::Path;
+
+// 缩略图宽度（像素），高度按比例
+pub const WIDTH: u32 = 320;
+
+// 解码后的 RGBA 缩略图
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+// 截取 at 秒处的一帧，以 PNG 格式输出到内存
+pub fn png_frame(tools: &Tools, input: &Path, at: f64) -> Result<Vec<u8>, String> {
+    let args: Vec<OsString> = vec![
+        "-v".into(),
+        "error".into(),
+        "-ss".into(),
+        format!("{:.3}", at).into(),
+        "-i".into(),
+        input.into(),
+        "-frames:v".into(),
+        "1".into(),
+        "-vf".into(),
+        format!("scale={}:-2", WIDTH).into(),
+        "-f".into(),
+        "image2pipe".into(),
+        "-c:v".into(),
+        "png".into(),
+        "-".into(),
+    ];
+    let output = tools.execute_ffmpeg(&args, "生成缩略图")?;
+    if output.stdout.is_empty() {
+        return Err("未能截取画面".to_string());
+    }
+    Ok(output.stdout)
+}
+
+pub fn decode(png: &[u8]) -> Result<Thumbnail, String> {
+    let image = image::load_from_memory_with_format(png, image::ImageFormat::Png)
+        .map_err(|e| format!("无法解码缩略图: {}", e))?
+        .to_rgba8();
+    Ok(Thumbnail {
+        width: image.width(),
+        height: image.height(),
+        rgba: image.into_raw(),
+    })
+}
+
+// 取时长 10% 处的画面，避开片头黑场；时长未知时取第 1 秒
+pub fn thumbnail(tools: &Tools, input: &Path, duration: Option<f64>) -> Result<Thumbnail, String> {
+    let at = duration.map_or(1.0, |d| d * 0.1);
+    decode(&png_frame(tools, input, at)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{self, Reply, StubRunner};
+
+    // 4×2 的纯红 PNG
+    fn red_png() -> Vec<u8> {
+        let image = image::RgbaImage::from_pixel(4, 2, image::Rgba([255, 0, 0, 255]));
+        let mut png = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut png, image::ImageFormat::Png).unwrap();
+        png.into_inner()
+    }
+
+    #[test]
+    fn grabs_one_png_frame_at_position() {
+        let png = red_png();
+        let reply = png.clone();
+        let runner = StubRunner::new(move |_, _| Reply::bytes(reply.clone()));
+
+        assert_eq!(
+            png_frame(&runner.tools(), Path::new("in.mp4"), 12.3456).unwrap(),
+            png
+        );
+        assert_eq!(
+            stub::calls_to(&runner, "ffmpeg"),
+            [[
+                "-v",
+                "error",
+                "-ss",
+                "12.346",
+                "-i",
+                "in.mp4",
+                "-frames:v",
+                "1",
+                "-vf",
+                "scale=320:-2",
+                "-f",
+                "image2pipe",
+                "-c:v",
+                "png",
+                "-"
+            ]]
+        );
+    }
+
+    #[test]
+    fn thumbnail_seeks_past_the_opening() {
+        let png = red_png();
+        let runner = StubRunner::new(move |_, _| Reply::bytes(png.clone()));
+        let tools = runner.tools();
+
+        let grabbed = thumbnail(&tools, Path::new("in.mp4"), Some(50.0)).unwrap();
+        assert_eq!((grabbed.width, grabbed.height), (4, 2));
+        assert_eq!(grabbed.rgba[..4], [255, 0, 0, 255]);
+        thumbnail(&tools, Path::new("in.mp4"), None).unwrap();
+
+        let seeks: Vec<String> = stub::calls_to(&runner, "ffmpeg")
+            .iter()
+            .map(|args| args[3].clone())
+            .collect();
+        assert_eq!(seeks, ["5.000", "1.000"]);
+    }
+
+    #[test]
+    fn empty_output_is_an_error() {
+        let runner = StubRunner::new(|_, _| Reply::default());
+        assert_eq!(
+            png_frame(&runner.tools(), Path::new("in.mp4"), 1.0).unwrap_err(),
+            "未能截取画面"
+        );
+    }
+
+    #[test]
+    fn non_png_output_is_an_error() {
+        let runner = StubRunner::new(|_, _| Reply::ok("not a png"));
+        let error = thumbnail(&runner.tools(), Path::new("in.mp4"), None)
+            .err()
+            .unwrap();
+        assert!(error.starts_with("无法解码缩略图"), "{}", error);
+    }
+
+    #[test]
+    fn ffmpeg_failure_is_reported() {
+        let runner = StubRunner::new(|_, _| Reply::fail(1, "in.mp4: Invalid data found"));
+        let error = png_frame(&runner.tools(), Path::new("in.mp4"), 1.0).unwrap_err();
+        assert!(error.starts_with("生成缩略图失败（退出码 1）"), "{}", error);
+    }
+}
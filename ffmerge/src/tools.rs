@@ -0,0 +1,373 @@
+use crate::log::{JobLog, LogEntry, LogLine, Source};
+use crate::runner::{ProcessRunner, Run, Runner};
+use chrono::Local;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::sync::Arc;
+use std::time::Instant;
+
+// 支持的最低 ffmpeg 版本，转码的帧率上限使用的 -fpsmax 需要 4.4
+pub const MIN_VERSION: (u32, u32) = (4, 4);
+
+// ffmpeg/ffprobe 可执行文件位置
+#[derive(Clone, Debug)]
+pub struct Tools {
+    pub ffmpeg: PathBuf,
+    pub ffprobe: PathBuf,
+    // execute_* 的调用记录
+    pub log: JobLog,
+    pub runner: Arc<dyn Runner>,
+}
+
+impl Default for Tools {
+    fn default() -> Self {
+        Self {
+            ffmpeg: PathBuf::from("ffmpeg"),
+            ffprobe: PathBuf::from("ffprobe"),
+            log: JobLog::default(),
+            runner: Arc::new(ProcessRunner),
+        }
+    }
+}
+
+impl Tools {
+    // 依次尝试环境变量、PATH 与常见安装目录
+    pub fn detect() -> Self {
+        Self {
+            ffmpeg: detect_binary("ffmpeg", "FFMPEG_PATH"),
+            ffprobe: detect_binary("ffprobe", "FFPROBE_PATH"),
+            ..Default::default()
+        }
+    }
+
+    pub fn run_ffprobe(&self, args: &[OsString]) -> std::io::Result<Output> {
+        self.runner.run(&self.ffprobe, args).map(|run| run.output)
+    }
+
+    // 执行 ffmpeg 并记入日志，启动失败或返回非零时以 stderr 末行生成错误描述
+    pub fn execute_ffmpeg(&self, args: &[OsString], action: &str) -> Result<Output, String> {
+        self.execute(&self.ffmpeg, args, action)
+    }
+
+    pub fn execute_ffprobe(&self, args: &[OsString], action: &str) -> Result<Output, String> {
+        self.execute(&self.ffprobe, args, action)
+    }
+
+    // 用于预览的完整命令行
+    pub fn ffmpeg_command_line(&self, args: &[OsString]) -> String {
+        command_line(&self.ffmpeg, args)
+    }
+
+    fn execute(&self, program: &Path, args: &[OsString], action: &str) -> Result<Output, String> {
+        let started = Local::now();
+        let timer = Instant::now();
+        let result = self.runner.run(program, args);
+
+        let mut entry = LogEntry {
+            action: action.to_string(),
+            command: command_line(program, args),
+            started,
+            lines: Vec::new(),
+            exit_code: None,
+            duration: timer.elapsed(),
+        };
+        let output = match result {
+            Ok(Run { output, lines }) => {
+                entry.lines = lines;
+                entry.exit_code = output.status.code();
+                output
+            }
+            Err(e) => {
+                let message = format!("执行错误: {}", e);
+                entry.lines.push(LogLine {
+                    time: Local::now(),
+                    source: Source::Stderr,
+                    text: message.clone(),
+                });
+                self.log.push(entry);
+                return Err(message);
+            }
+        };
+
+        let last_error = entry
+            .lines
+            .iter()
+            .rev()
+            .find(|l| l.source == Source::Stderr && !l.text.trim().is_empty())
+            .map(|l| l.text.trim().to_string())
+            .unwrap_or_default();
+        let success = entry.is_success();
+        self.log.push(entry);
+
+        if !success {
+            return Err(format!(
+                "{}失败（退出码 {}）: {}",
+                action,
+                output
+                    .status
+                    .code()
+                    .map_or_else(|| "无".to_string(), |c| c.to_string()),
+                last_error
+            ));
+        }
+        Ok(output)
+    }
+
+    // 检查两个程序是否可用且版本满足要求，返回诊断信息
+    pub fn check(&self) -> Result<String, String> {
+        let ffmpeg = check_version(self.runner.as_ref(), &self.ffmpeg)?;
+        let ffprobe = check_version(self.runner.as_ref(), &self.ffprobe)?;
+        Ok(format!("ffmpeg {} / ffprobe {}", ffmpeg, ffprobe))
+    }
+}
+
+// 含空白或引号的参数加引号
+fn command_line(program: &Path, args: &[OsString]) -> String {
+    std::iter::once(program.as_os_str())
+        .chain(args.iter().map(OsString::as_os_str))
+        .map(|arg| quote(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn quote(arg: &str) -> String {
+    if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || "\"'".contains(c)) {
+        return arg.to_string();
+    }
+    format!("\"{}\"", arg.replace('"', "\\\""))
+}
+
+fn candidates(name: &str) -> Vec<PathBuf> {
+    let exe = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    let mut dirs: Vec<PathBuf> = Vec::new();
+
+    #[cfg(windows)]
+    {
+        for var in ["ProgramFiles", "ProgramFiles(x86)"] {
+            if let Some(dir) = std::env::var_os(var) {
+                dirs.push(PathBuf::from(dir).join("ffmpeg").join("bin"));
+            }
+        }
+        if let Some(dir) = std::env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(dir).join("Microsoft\\WinGet\\Links"));
+        }
+        dirs.push(PathBuf::from("C:\\ffmpeg\\bin"));
+    }
+    #[cfg(not(windows))]
+    {
+        dirs.extend(
+            [
+                "/usr/bin",
+                "/usr/local/bin",
+                "/opt/homebrew/bin",
+                "/opt/local/bin",
+                "/snap/bin",
+            ]
+            .map(PathBuf::from),
+        );
+    }
+
+    // 与本程序放在同一目录
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(Path::to_path_buf))
+    {
+        dirs.push(dir);
+    }
+
+    dirs.into_iter().map(|dir| dir.join(&exe)).collect()
+}
+
+fn detect_binary(name: &str, env_var: &str) -> PathBuf {
+    if let Some(path) = std::env::var_os(env_var) {
+        return PathBuf::from(path);
+    }
+
+    std::iter::once(PathBuf::from(name))
+        .chain(candidates(name))
+        .find(|path| version_line(&ProcessRunner, path).is_ok())
+        .unwrap_or_else(|| PathBuf::from(name))
+}
+
+// 读取 `-version` 输出的第一行
+fn version_line(runner: &dyn Runner, program: &Path) -> Result<String, String> {
+    let output = runner
+        .run(program, &["-version".into()])
+        .map_err(|e| format!("找不到 {}: {}", program.display(), e))?
+        .output;
+    if !output.status.success() {
+        return Err(format!("{} -version 执行失败", program.display()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
+
+// 从 "ffmpeg version 6.1.1-..." 中解析主次版本号，git 构建（N-xxxxx）返回 None
+fn parse_version(line: &str) -> Option<(u32, u32)> {
+    let version = line.split_whitespace().nth(2)?;
+    let version = version.strip_prefix('n').unwrap_or(version);
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().and_then(|p| p.parse().ok()).unwrap_or(0);
+    Some((major, minor))
+}
+
+fn check_version(runner: &dyn Runner, program: &Path) -> Result<String, String> {
+    let line = version_line(runner, program)?;
+    match parse_version(&line) {
+        Some(version) if version < MIN_VERSION => Err(format!(
+            "{} 版本 {}.{} 过旧，至少需要 {}.{}",
+            program.display(),
+            version.0,
+            version.1,
+            MIN_VERSION.0,
+            MIN_VERSION.1
+        )),
+        Some((major, minor)) => Ok(format!("{}.{}", major, minor)),
+        None => Ok(line.split_whitespace().nth(2).unwrap_or("?").to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{Reply, StubRunner};
+
+    #[test]
+    fn parses_release_and_git_versions() {
+        assert_eq!(parse_version("ffmpeg version 6.1.1-static"), Some((6, 1)));
+        assert_eq!(parse_version("ffmpeg version n4.4.2"), Some((4, 4)));
+        assert_eq!(parse_version("ffmpeg version 7 Copyright"), Some((7, 0)));
+        assert_eq!(parse_version("ffmpeg version N-112345-gabcdef"), None);
+    }
+
+    #[test]
+    fn check_rejects_old_version() {
+        let runner = StubRunner::new(|_, _| Reply::ok("ffmpeg version 3.4.8\n"));
+        let err = runner.tools().check().unwrap_err();
+        assert!(err.contains("3.4"), "{}", err);
+
+        // -fpsmax 需要 4.4
+        let runner = StubRunner::new(|_, _| Reply::ok("ffmpeg version 4.3.2\n"));
+        let err = runner.tools().check().unwrap_err();
+        assert!(err.contains("至少需要 4.4"), "{}", err);
+        let runner = StubRunner::new(|_, _| Reply::ok("ffmpeg version 4.4.2\n"));
+        assert_eq!(runner.tools().check().unwrap(), "ffmpeg 4.4 / ffprobe 4.4");
+    }
+
+    #[test]
+    fn check_accepts_git_build() {
+        let runner = StubRunner::new(|_, _| Reply::ok("ffmpeg version N-112345-gabcdef\n"));
+        assert_eq!(
+            runner.tools().check().unwrap(),
+            "ffmpeg N-112345-gabcdef / ffprobe N-112345-gabcdef"
+        );
+    }
+
+    #[test]
+    fn failure_reports_exit_code_and_last_stderr_line() {
+        let runner = StubRunner::new(|_, _| {
+            Reply::fail(
+                1,
+                "Input #0, matroska\nmissing.mkv: No such file or directory\n",
+            )
+        });
+        let tools = runner.tools();
+
+        let err = tools.execute_ffmpeg(&["-i".into()], "转换").unwrap_err();
+        assert_eq!(
+            err,
+            "转换失败（退出码 1）: missing.mkv: No such file or directory"
+        );
+
+        let entries = tools.log.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].exit_code, Some(1));
+        assert_eq!(entries[0].command, "ffmpeg -i");
+    }
+
+    #[test]
+    fn missing_program_is_logged() {
+        let tools = Tools {
+            ffmpeg: "/nonexistent/ffmpeg".into(),
+            ..Default::default()
+        };
+
+        let err = tools.execute_ffmpeg(&[], "转换").unwrap_err();
+        assert!(err.starts_with("执行错误"), "{}", err);
+        let entries = tools.log.entries();
+        assert_eq!(entries[0].exit_code, None);
+        assert!(!entries[0].is_success());
+    }
+
+    #[test]
+    fn quotes_arguments_with_spaces() {
+        let args: Vec<OsString> = vec!["-i".into(), "my clip.mp4".into(), "".into()];
+        assert_eq!(
+            command_line(Path::new("ffmpeg"), &args),
+            "ffmpeg -i \"my clip.mp4\" \"\""
+        );
+    }
+
+    #[cfg(unix)]
+    mod script {
+        use super::*;
+        use crate::stub::StubScript;
+
+        #[test]
+        fn records_progress_and_output_lines() {
+            let stub = StubScript::new()
+                .progress()
+                .stdout("done\n")
+                .stderr("muxing overhead: 0.1%");
+            let tools = Tools {
+                ffmpeg: stub.install("ffmpeg"),
+                ..Default::default()
+            };
+
+            let output = tools.execute_ffmpeg(&["-y".into()], "转换").unwrap();
+            assert_eq!(output.stdout, b"done\n");
+
+            let entry = &tools.log.entries()[0];
+            assert_eq!(entry.exit_code, Some(0));
+            let texts: Vec<&str> = entry.lines.iter().map(|l| l.text.as_str()).collect();
+            // 进度行只保留最后一段
+            assert!(texts.contains(&"frame=3 time=00:00:03"), "{:?}", texts);
+            assert!(!texts.iter().any(|t| t.contains("frame=1")), "{:?}", texts);
+            assert!(texts.contains(&"done"));
+            assert!(texts.contains(&"muxing overhead: 0.1%"));
+            assert_eq!(stub.calls("ffmpeg"), vec![vec!["-y".to_string()]]);
+        }
+
+        #[test]
+        fn nonzero_exit_is_an_error() {
+            let stub = StubScript::new()
+                .stderr("Unknown encoder 'libfoo'")
+                .exit_code(8);
+            let tools = Tools {
+                ffmpeg: stub.install("ffmpeg"),
+                ..Default::default()
+            };
+
+            let err = tools.execute_ffmpeg(&[], "转码").unwrap_err();
+            assert_eq!(err, "转码失败（退出码 8）: Unknown encoder 'libfoo'");
+            assert_eq!(tools.log.entries()[0].exit_code, Some(8));
+        }
+
+        #[test]
+        fn check_runs_version_of_both_programs() {
+            let stub = StubScript::new().version("5.1.2");
+            let tools = Tools {
+                ffmpeg: stub.install("ffmpeg"),
+                ffprobe: stub.install("ffprobe"),
+                ..Default::default()
+            };
+            assert_eq!(tools.check().unwrap(), "ffmpeg 5.1 / ffprobe 5.1");
+        }
+    }
+}
@@ -0,0 +1,807 @@
+use crate::concat;
+use crate::probe::{self, StreamInfo};
+use crate::tools::Tools;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// 保留的片段（秒），end 为 None 表示到结尾
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+impl Segment {
+    // 解析 "开始-结束" 形式，结束可省略，如 "1:30-2:45" 或 "90-"
+    pub fn parse(text: &str) -> Option<Self> {
+        let (start, end) = text.split_once('-')?;
+        let start = parse_time(start)?;
+        let end = if end.trim().is_empty() {
+            None
+        } else {
+            Some(parse_time(end)?)
+        };
+        Segment::new(start, end)
+    }
+
+    pub fn new(start: f64, end: Option<f64>) -> Option<Self> {
+        match end {
+            Some(end) if end <= start => None,
+            _ => Some(Self { start, end }),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let end = self.end.map(format_time).unwrap_or_else(|| "结尾".into());
+        format!("{} - {}", format_time(self.start), end)
+    }
+}
+
+// 解析 "90"、"1:30"、"01:02:03.5" 等时间写法
+pub fn parse_time(text: &str) -> Option<f64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    let mut seconds = 0.0;
+    for part in text.split(':') {
+        let value: f64 = part.trim().parse().ok()?;
+        if value < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Some(seconds)
+}
+
+pub fn format_time(seconds: f64) -> String {
+    let ms = (seconds * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+// 读取第一条视频流所有关键帧的时间戳
+pub fn keyframes(tools: &Tools, input: &Path) -> Result<Vec<f64>, String> {
+    let args: Vec<OsString> = vec![
+        "-v".into(),
+        "error".into(),
+        "-select_streams".into(),
+        "v:0".into(),
+        "-show_entries".into(),
+        "packet=pts_time,flags".into(),
+        "-of".into(),
+        "csv=p=0".into(),
+        input.into(),
+    ];
+    let output = tools.execute_ffprobe(&args, "读取关键帧")?;
+
+    let mut keyframes: Vec<f64> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pts, flags) = line.split_once(',')?;
+            flags.contains('K').then(|| pts.parse().ok())?
+        })
+        .collect();
+    keyframes.sort_by(f64::total_cmp);
+    Ok(keyframes)
+}
+
+// 不晚于 t 的最后一个关键帧
+fn keyframe_before(keyframes: &[f64], t: f64) -> f64 {
+    keyframes
+        .iter()
+        .rev()
+        .find(|&&k| k <= t + 0.001)
+        .copied()
+        .unwrap_or(0.0)
+}
+
+// 晚于 t 的第一个关键帧
+fn keyframe_after(keyframes: &[f64], t: f64) -> Option<f64> {
+    keyframes.iter().find(|&&k| k > t + 0.001).copied()
+}
+
+// 与原视频编码对应的编码器及高质量参数，避免编码器默认值降低画质
+fn encoder_for(codec: &str) -> Option<(&'static str, &'static [&'static str])> {
+    match codec {
+        "h264" => Some(("libx264", &["-crf", "16", "-preset", "slow"])),
+        "hevc" => Some(("libx265", &["-crf", "18", "-preset", "slow"])),
+        "vp9" => Some(("libvpx-vp9", &["-crf", "20", "-b:v", "0", "-row-mt", "1"])),
+        "av1" => Some(("libsvtav1", &["-crf", "24", "-preset", "6"])),
+        "mpeg4" => Some(("mpeg4", &["-q:v", "2"])),
+        _ => None,
+    }
+}
+
+// ffprobe 报告的档次对应的 x264/x265 profile
+fn encoder_profile(codec: &str, profile: &str) -> Option<&'static str> {
+    match (codec, profile) {
+        ("h264", "Constrained Baseline" | "Baseline") => Some("baseline"),
+        ("h264", "Main") => Some("main"),
+        ("h264", "High") => Some("high"),
+        ("h264", "High 10") => Some("high10"),
+        ("h264", "High 4:2:2") => Some("high422"),
+        ("h264", "High 4:4:4 Predictive") => Some("high444"),
+        ("hevc", "Main") => Some("main"),
+        ("hevc", "Main 10") => Some("main10"),
+        _ => None,
+    }
+}
+
+// 按原视频流的编码、档次、级别与像素格式生成重新编码参数
+fn video_encode_args(stream: &StreamInfo) -> Option<Vec<OsString>> {
+    let (encoder, quality) = encoder_for(&stream.codec_name)?;
+    let mut args: Vec<OsString> = vec!["-c:v".into(), encoder.into()];
+    args.extend(quality.iter().map(OsString::from));
+    if !stream.pix_fmt.is_empty() {
+        args.extend(["-pix_fmt".into(), stream.pix_fmt.clone().into()]);
+    }
+    if let Some(profile) = encoder_profile(&stream.codec_name, &stream.profile) {
+        args.extend(["-profile:v".into(), profile.into()]);
+    }
+    // ffprobe 中 H.264 的级别为 10 倍值，HEVC 为 30 倍值
+    match (stream.codec_name.as_str(), stream.level) {
+        ("h264", Some(level)) if level >= 10 => args.extend([
+            "-level:v".into(),
+            format!("{}.{}", level / 10, level % 10).into(),
+        ]),
+        ("hevc", Some(level)) => args.extend([
+            "-x265-params".into(),
+            format!("level-idc={}", level as f64 / 30.0).into(),
+        ]),
+        _ => {}
+    }
+    Some(args)
+}
+
+// 可以写入 MPEG-TS 中间文件的音频编码
+const TS_AUDIO: [&str; 6] = ["aac", "mp3", "mp2", "ac3", "eac3", "opus"];
+
+// 精确剪切方式
+enum Accurate {
+    // H.264/HEVC 只重新编码起点所在的 GOP，其余部分流复制。
+    // 各部分写为 MPEG-TS，参数集随关键帧内嵌在码流中，重新编码部分与复制部分的参数集不同也能正确解码；
+    // MPEG-TS 只能容纳视频与音频，封面与字幕不保留
+    Smart {
+        encode: Vec<OsString>,
+        // 原视频流的时间基分母，输出 MP4/MOV 时沿用
+        timescale: Option<u32>,
+    },
+    // 其他编码或音频无法写入 MPEG-TS 时重新编码整个片段
+    Whole(Vec<OsString>),
+}
+
+impl Accurate {
+    fn probe(tools: &Tools, input: &Path) -> Result<Self, String> {
+        let info = probe::probe(tools, input).ok_or("无法探测输入文件")?;
+        let video = info.first_stream("video").cloned().unwrap_or_default();
+        let encode = video_encode_args(&video)
+            .ok_or_else(|| format!("不支持精确剪切 {} 编码", video.codec_name))?;
+        let ts_audio = info
+            .streams
+            .iter()
+            .filter(|s| s.codec_type == "audio")
+            .all(|s| TS_AUDIO.contains(&s.codec_name.as_str()));
+
+        if matches!(video.codec_name.as_str(), "h264" | "hevc") && ts_audio {
+            let timescale = video
+                .time_base
+                .split_once('/')
+                .and_then(|(_, den)| den.parse().ok());
+            Ok(Accurate::Smart { encode, timescale })
+        } else {
+            Ok(Accurate::Whole(encode))
+        }
+    }
+
+    fn encode(&self) -> &[OsString] {
+        match self {
+            Accurate::Smart { encode, .. } | Accurate::Whole(encode) => encode,
+        }
+    }
+}
+
+// 剪切时单独生成的一段
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Part {
+    start: f64,
+    end: Option<f64>,
+    // 重新编码视频，否则流复制
+    encode: bool,
+}
+
+// 起点不在关键帧上时，重新编码到下一个关键帧，之后流复制；片段内没有后续关键帧时整段重新编码
+fn smart_parts(keyframes: &[f64], segment: &Segment) -> Vec<Part> {
+    let copy = |start| Part {
+        start,
+        end: segment.end,
+        encode: false,
+    };
+    if (segment.start - keyframe_before(keyframes, segment.start)).abs() <= 0.001 {
+        return vec![copy(segment.start)];
+    }
+
+    let head = |end| Part {
+        start: segment.start,
+        end,
+        encode: true,
+    };
+    match keyframe_after(keyframes, segment.start)
+        .filter(|&k| segment.end.is_none_or(|end| k < end - 0.001))
+    {
+        Some(k) => vec![head(Some(k)), copy(k)],
+        None => vec![head(segment.end)],
+    }
+}
+
+// ts 为 true 时只保留视频与音频并写为 MPEG-TS
+fn part_args(
+    input: &Path,
+    part: &Part,
+    encode: &[OsString],
+    ts: bool,
+    output: &Path,
+) -> Vec<OsString> {
+    // 流复制从关键帧开始，时间需与 ffprobe 报告的关键帧时间完全一致
+    let mut args: Vec<OsString> = vec![
+        "-y".into(),
+        "-ss".into(),
+        format!("{:.6}", part.start).into(),
+        "-i".into(),
+        input.into(),
+    ];
+    if let Some(end) = part.end {
+        args.extend(["-t".into(), format!("{:.6}", end - part.start).into()]);
+    }
+    if part.encode {
+        args.extend(encode.iter().cloned());
+        args.extend(["-c:a".into(), "copy".into()]);
+    } else {
+        args.extend(["-c".into(), "copy".into()]);
+    }
+    if ts {
+        args.extend([
+            "-map".into(),
+            "0:v:0".into(),
+            "-map".into(),
+            "0:a?".into(),
+            "-f".into(),
+            "mpegts".into(),
+        ]);
+    } else {
+        args.extend(["-map".into(), "0".into()]);
+    }
+    args.extend([
+        "-avoid_negative_ts".into(),
+        "make_zero".into(),
+        output.into(),
+    ]);
+    args
+}
+
+fn run(tools: &Tools, args: &[OsString]) -> Result<(), String> {
+    tools.execute_ffmpeg(args, "剪切").map(|_| ())
+}
+
+// 剪切过程中的临时目录，离开作用域时删除
+pub struct WorkDir {
+    path: PathBuf,
+}
+
+impl WorkDir {
+    pub fn create() -> Result<Self, String> {
+        // 同一进程内可能同时创建多个目录，加上计数避免重名
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let path = std::env::temp_dir().join(format!(
+            "ffmerge-{}-{}-{}",
+            std::process::id(),
+            nanos,
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).map_err(|e| format!("无法创建临时目录: {}", e))?;
+        Ok(Self { path })
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+// 按片段剪切并拼接，返回实际保留的片段
+//
+// 流复制只能从关键帧开始，默认将起点前移到最近的关键帧。
+// accurate 为 true 时从指定时间开始：H.264/HEVC 只重新编码起点到下一个关键帧之间的画面，
+// 其他编码重新编码整个片段
+pub fn cut(
+    tools: &Tools,
+    input: &Path,
+    output: &Path,
+    segments: &[Segment],
+    accurate: bool,
+    overwrite: bool,
+) -> Result<Vec<Segment>, String> {
+    let accurate = match accurate {
+        true => Some(Accurate::probe(tools, input)?),
+        false => None,
+    };
+    let keyframes = match accurate {
+        Some(Accurate::Whole(_)) => Vec::new(),
+        _ => keyframes(tools, input)?,
+    };
+
+    let work = WorkDir::create()?;
+    let output_ext = output
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_else(|| "mp4".into());
+    let smart = matches!(accurate, Some(Accurate::Smart { .. }));
+    let ext = if smart { "ts" } else { output_ext.as_str() };
+    let encode = accurate.as_ref().map_or(&[][..], Accurate::encode);
+    let mut parts = Vec::new();
+    let mut actual = Vec::new();
+
+    for segment in segments {
+        let segment_parts = match accurate {
+            None => vec![Part {
+                start: keyframe_before(&keyframes, segment.start),
+                end: segment.end,
+                encode: false,
+            }],
+            Some(Accurate::Whole(_)) => vec![Part {
+                start: segment.start,
+                end: segment.end,
+                encode: true,
+            }],
+            Some(Accurate::Smart { .. }) => smart_parts(&keyframes, segment),
+        };
+        actual.push(Segment {
+            start: segment_parts[0].start,
+            ..*segment
+        });
+        for part in &segment_parts {
+            let path = work.join(&format!("part{}.{}", parts.len(), ext));
+            run(tools, &part_args(input, part, encode, smart, &path))?;
+            parts.push(path);
+        }
+    }
+
+    let list = work.join("concat.txt");
+    std::fs::write(&list, concat::demuxer_list(&parts))
+        .map_err(|e| format!("无法写入列表: {}", e))?;
+    let mut args = concat::demuxer_args(&list, output, overwrite);
+    if let Some(Accurate::Smart {
+        timescale: Some(timescale),
+        ..
+    }) = accurate
+    {
+        if matches!(output_ext.as_str(), "mp4" | "m4v" | "mov") {
+            let at = args.len() - 1;
+            args.splice(
+                at..at,
+                [
+                    "-video_track_timescale".into(),
+                    timescale.to_string().into(),
+                ],
+            );
+        }
+    }
+    run(tools, &args)?;
+
+    Ok(actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stub::{self, strings, Reply, StubRunner};
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time("90"), Some(90.0));
+        assert_eq!(parse_time(" 1:30 "), Some(90.0));
+        assert_eq!(parse_time("01:02:03.5"), Some(3723.5));
+        assert_eq!(parse_time(""), None);
+        assert_eq!(parse_time("1:-5"), None);
+        assert_eq!(parse_time("abc"), None);
+    }
+
+    #[test]
+    fn parses_segments() {
+        assert_eq!(
+            Segment::parse("1:30-2:45"),
+            Some(Segment {
+                start: 90.0,
+                end: Some(165.0)
+            })
+        );
+        assert_eq!(
+            Segment::parse("90-"),
+            Some(Segment {
+                start: 90.0,
+                end: None
+            })
+        );
+        assert_eq!(Segment::parse("20-10"), None);
+        assert_eq!(Segment::parse("10-10"), None);
+        assert_eq!(Segment::parse("10"), None);
+        assert_eq!(
+            Segment::parse("90-").unwrap().describe(),
+            "00:01:30.000 - 结尾"
+        );
+    }
+
+    #[test]
+    fn finds_keyframe_before() {
+        let keyframes = [0.0, 2.0, 4.0];
+        assert_eq!(keyframe_before(&keyframes, 3.9), 2.0);
+        assert_eq!(keyframe_before(&keyframes, 4.0), 4.0);
+        assert_eq!(keyframe_before(&keyframes, 3.9995), 4.0);
+        assert_eq!(keyframe_before(&keyframes, 9.0), 4.0);
+        assert_eq!(keyframe_before(&[], 9.0), 0.0);
+    }
+
+    #[test]
+    fn finds_keyframe_after() {
+        let keyframes = [0.0, 2.0, 4.0];
+        assert_eq!(keyframe_after(&keyframes, 0.0), Some(2.0));
+        assert_eq!(keyframe_after(&keyframes, 2.5), Some(4.0));
+        assert_eq!(keyframe_after(&keyframes, 3.9995), None);
+        assert_eq!(keyframe_after(&keyframes, 4.0), None);
+    }
+
+    #[test]
+    fn splits_segments_at_next_keyframe() {
+        let keyframes = [0.0, 2.0, 4.0];
+        let part = |start, end, encode| Part { start, end, encode };
+        let parts = |text| smart_parts(&keyframes, &Segment::parse(text).unwrap());
+
+        assert_eq!(parts("2-5"), [part(2.0, Some(5.0), false)]);
+        assert_eq!(
+            parts("3-5"),
+            [part(3.0, Some(4.0), true), part(4.0, Some(5.0), false)]
+        );
+        assert_eq!(
+            parts("1-"),
+            [part(1.0, Some(2.0), true), part(2.0, None, false)]
+        );
+        // 片段内没有后续关键帧时整段重新编码
+        assert_eq!(parts("2.5-4"), [part(2.5, Some(4.0), true)]);
+        assert_eq!(parts("4.5-"), [part(4.5, None, true)]);
+    }
+
+    #[test]
+    fn matches_source_encoding_parameters() {
+        let info = probe::parse(&stub::probe_output(1.0, &[("video", "h264")]));
+        assert_eq!(
+            strings(&video_encode_args(&info.streams[0]).unwrap()),
+            [
+                "-c:v",
+                "libx264",
+                "-crf",
+                "16",
+                "-preset",
+                "slow",
+                "-pix_fmt",
+                "yuv420p",
+                "-profile:v",
+                "high",
+                "-level:v",
+                "4.0"
+            ]
+        );
+
+        let hevc = StreamInfo {
+            codec_name: "hevc".into(),
+            pix_fmt: "yuv420p10le".into(),
+            profile: "Main 10".into(),
+            level: Some(123),
+            ..Default::default()
+        };
+        let args = strings(&video_encode_args(&hevc).unwrap());
+        assert_eq!(args[..2], ["-c:v", "libx265"]);
+        assert!(args.windows(2).any(|w| w == ["-profile:v", "main10"]));
+        assert!(args
+            .windows(2)
+            .any(|w| w == ["-x265-params", "level-idc=4.1"]));
+
+        let vp9 = StreamInfo {
+            codec_name: "vp9".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            strings(&video_encode_args(&vp9).unwrap()),
+            [
+                "-c:v",
+                "libvpx-vp9",
+                "-crf",
+                "20",
+                "-b:v",
+                "0",
+                "-row-mt",
+                "1"
+            ]
+        );
+        assert!(video_encode_args(&StreamInfo::default()).is_none());
+    }
+
+    #[test]
+    fn builds_part_args() {
+        let copy = Part {
+            start: 2.0,
+            end: Some(5.5),
+            encode: false,
+        };
+        assert_eq!(
+            strings(&part_args(
+                Path::new("in.mp4"),
+                &copy,
+                &[],
+                false,
+                Path::new("p.mp4")
+            )),
+            [
+                "-y",
+                "-ss",
+                "2.000000",
+                "-i",
+                "in.mp4",
+                "-t",
+                "3.500000",
+                "-c",
+                "copy",
+                "-map",
+                "0",
+                "-avoid_negative_ts",
+                "make_zero",
+                "p.mp4"
+            ]
+        );
+
+        let encode = Part {
+            start: 2.0,
+            end: None,
+            encode: true,
+        };
+        let video: Vec<OsString> = vec!["-c:v".into(), "libx264".into()];
+        assert_eq!(
+            strings(&part_args(
+                Path::new("in.mp4"),
+                &encode,
+                &video,
+                true,
+                Path::new("p.ts")
+            )),
+            [
+                "-y",
+                "-ss",
+                "2.000000",
+                "-i",
+                "in.mp4",
+                "-c:v",
+                "libx264",
+                "-c:a",
+                "copy",
+                "-map",
+                "0:v:0",
+                "-map",
+                "0:a?",
+                "-f",
+                "mpegts",
+                "-avoid_negative_ts",
+                "make_zero",
+                "p.ts"
+            ]
+        );
+    }
+
+    // 关键帧位于 0、2、4 秒的桩 ffprobe，ffmpeg 调用总是成功
+    fn stub() -> std::sync::Arc<StubRunner> {
+        StubRunner::new(|program, args| {
+            if !program.ends_with("ffprobe") {
+                return Reply::default();
+            }
+            if args.iter().any(|a| a == "packet=pts_time,flags") {
+                Reply::ok("4.000000,__\n0.000000,K_\n2.000000,K_\n3.000000,__\n4.000000,K_\n")
+            } else {
+                Reply::ok(&stub::probe_output(
+                    10.0,
+                    &[("video", "h264"), ("audio", "aac")],
+                ))
+            }
+        })
+    }
+
+    #[test]
+    fn reads_keyframes() {
+        let runner = stub();
+        let keyframes = keyframes(&runner.tools(), Path::new("in.mp4")).unwrap();
+        assert_eq!(keyframes, [0.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn copy_cut_starts_at_keyframes() {
+        let runner = stub();
+        let segments = [
+            Segment::parse("3-5").unwrap(),
+            Segment::parse("8-").unwrap(),
+        ];
+
+        let actual = cut(
+            &runner.tools(),
+            Path::new("in.mp4"),
+            Path::new("out.mp4"),
+            &segments,
+            false,
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            actual,
+            [
+                Segment {
+                    start: 2.0,
+                    end: Some(5.0)
+                },
+                Segment {
+                    start: 4.0,
+                    end: None
+                }
+            ]
+        );
+
+        let calls = stub::calls_to(&runner, "ffmpeg");
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0][1..5], ["-ss", "2.000000", "-i", "in.mp4"]);
+        assert!(calls[0].contains(&"copy".to_string()));
+        assert_eq!(calls[1][2], "4.000000");
+        assert_eq!(calls[2][..3], ["-y", "-f", "concat"]);
+        assert_eq!(calls[2].last().map(String::as_str), Some("out.mp4"));
+    }
+
+    // 剪切时各段 ffmpeg 调用的参数，不含最后的拼接
+    fn part_calls(runner: &StubRunner) -> Vec<Vec<String>> {
+        stub::calls_to(runner, "ffmpeg")
+            .into_iter()
+            .filter(|args| args[1] == "-ss")
+            .collect()
+    }
+
+    #[test]
+    fn accurate_cut_reencodes_only_the_first_gop() {
+        let runner = stub();
+        let segments = [
+            Segment::parse("3-5").unwrap(),
+            Segment::parse("6-7").unwrap(),
+            Segment::parse("2-3").unwrap(),
+        ];
+
+        let actual = cut(
+            &runner.tools(),
+            Path::new("in.mp4"),
+            Path::new("out.mp4"),
+            &segments,
+            true,
+            false,
+        )
+        .unwrap();
+        assert_eq!(actual, segments);
+
+        let parts = part_calls(&runner);
+        let summary: Vec<(&str, bool)> = parts
+            .iter()
+            .map(|p| (p[2].as_str(), p.contains(&"libx264".to_string())))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("3.000000", true),
+                ("4.000000", false),
+                ("6.000000", true),
+                ("2.000000", false)
+            ]
+        );
+        assert!(parts[0].windows(2).any(|w| w == ["-t", "1.000000"]));
+        assert!(parts
+            .iter()
+            .all(|p| p.windows(2).any(|w| w == ["-f", "mpegts"])));
+        assert!(parts.iter().all(|p| p.last().unwrap().ends_with(".ts")));
+
+        let concat = stub::calls_to(&runner, "ffmpeg").pop().unwrap();
+        assert_eq!(concat[..3], ["-n", "-f", "concat"]);
+        assert_eq!(
+            concat[concat.len() - 3..],
+            ["-video_track_timescale", "15360", "out.mp4"]
+        );
+    }
+
+    #[test]
+    fn accurate_cut_reencodes_whole_segments_of_other_codecs() {
+        let runner = StubRunner::new(|_, _| {
+            Reply::ok(&stub::probe_output(
+                10.0,
+                &[("video", "vp9"), ("audio", "opus")],
+            ))
+        });
+        let segments = [Segment::parse("3-5").unwrap()];
+
+        cut(
+            &runner.tools(),
+            Path::new("in.webm"),
+            Path::new("out.webm"),
+            &segments,
+            true,
+            false,
+        )
+        .unwrap();
+        // 不需要读取关键帧
+        assert!(!stub::calls_to(&runner, "ffprobe")
+            .iter()
+            .any(|args| args.contains(&"packet=pts_time,flags".to_string())));
+        let parts = part_calls(&runner);
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].windows(2).any(|w| w == ["-c:v", "libvpx-vp9"]));
+        assert!(parts[0].windows(2).any(|w| w == ["-t", "2.000000"]));
+        assert!(parts[0].last().unwrap().ends_with(".webm"));
+        assert!(!stub::calls_to(&runner, "ffmpeg")
+            .pop()
+            .unwrap()
+            .contains(&"-video_track_timescale".to_string()));
+    }
+
+    #[test]
+    fn audio_unsupported_by_mpegts_reencodes_whole_segments() {
+        let runner = StubRunner::new(|_, _| {
+            Reply::ok(&stub::probe_output(
+                10.0,
+                &[("video", "h264"), ("audio", "flac")],
+            ))
+        });
+
+        cut(
+            &runner.tools(),
+            Path::new("in.mkv"),
+            Path::new("out.mkv"),
+            &[Segment::parse("3-5").unwrap()],
+            true,
+            false,
+        )
+        .unwrap();
+        let parts = part_calls(&runner);
+        assert_eq!(parts.len(), 1);
+        assert!(parts[0].windows(2).any(|w| w == ["-c:v", "libx264"]));
+        assert!(parts[0].windows(2).any(|w| w == ["-map", "0"]));
+    }
+
+    #[test]
+    fn accurate_cut_rejects_unknown_codec() {
+        let runner =
+            StubRunner::new(|_, _| Reply::ok(&stub::probe_output(10.0, &[("video", "prores")])));
+        let err = cut(
+            &runner.tools(),
+            Path::new("in.mov"),
+            Path::new("out.mov"),
+            &[Segment::parse("1-2").unwrap()],
+            true,
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(err, "不支持精确剪切 prores 编码");
+    }
+}
@@ -0,0 +1,207 @@
+use crate::optimize::{self, FileResult, Job};
+use crate::output::OutputSettings;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+enum Update {
+    Started(usize, PathBuf),
+    Finished(usize, FileResult),
+}
+
+// 在后台线程中批量优化，界面每帧调用 poll 取回进度
+pub struct Batch {
+    pub total: usize,
+    // 正在处理的文件，按输入顺序
+    pub running: BTreeMap<usize, PathBuf>,
+    // 已完成的文件，按完成顺序
+    pub results: Vec<FileResult>,
+    order: Vec<usize>,
+    updates: Receiver<Update>,
+    cancel: Arc<AtomicBool>,
+    done: bool,
+}
+
+impl Batch {
+    // notify 在每个文件开始与结束时调用，用于唤醒界面
+    pub fn start(
+        files: Vec<PathBuf>,
+        root: PathBuf,
+        output: OutputSettings,
+        job: Job,
+        notify: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        let (sender, updates) = mpsc::channel();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = files.len();
+
+        let flag = cancel.clone();
+        std::thread::spawn(move || {
+            files
+                .par_iter()
+                .enumerate()
+                .for_each_with(sender, |sender, (i, path)| {
+                    // oxipng 没有外部中止接口，取消后只跳过尚未开始的文件
+                    if flag.load(Ordering::Relaxed) {
+                        return;
+                    }
+                    let _ = sender.send(Update::Started(i, path.clone()));
+                    notify();
+                    let target = output.target(path, &root);
+                    let result = optimize::optimize_file(path, &target, &job);
+                    let _ = sender.send(Update::Finished(i, result));
+                    notify();
+                });
+            notify();
+        });
+
+        Self {
+            total,
+            running: BTreeMap::new(),
+            results: Vec::new(),
+            order: Vec::new(),
+            updates,
+            cancel,
+            done: false,
+        }
+    }
+
+    fn apply(&mut self, update: Update) {
+        match update {
+            Update::Started(i, path) => {
+                self.running.insert(i, path);
+            }
+            Update::Finished(i, result) => {
+                self.running.remove(&i);
+                self.order.push(i);
+                self.results.push(result);
+            }
+        }
+    }
+
+    // 取回新的进度，全部结束后返回 true
+    pub fn poll(&mut self) -> bool {
+        while !self.done {
+            match self.updates.try_recv() {
+                Ok(update) => self.apply(update),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => self.done = true,
+            }
+        }
+        self.done
+    }
+
+    // 阻塞直到全部结束，命令行模式使用
+    pub fn wait(mut self) -> Vec<FileResult> {
+        while let Ok(update) = self.updates.recv() {
+            self.apply(update);
+        }
+        self.into_results()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    pub fn finished(&self) -> usize {
+        self.results.len()
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.finished() as f32 / self.total as f32
+        }
+    }
+
+    // 按输入顺序返回结果
+    pub fn into_results(self) -> Vec<FileResult> {
+        let mut results: Vec<_> = self.order.into_iter().zip(self.results).collect();
+        results.sort_by_key(|(i, _)| *i);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str) -> FileResult {
+        FileResult::new(name, name)
+    }
+
+    fn names(results: &[FileResult]) -> Vec<String> {
+        results
+            .iter()
+            .map(|r| r.path.display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn results_return_in_input_order() {
+        let (sender, updates) = mpsc::channel();
+        let mut batch = Batch {
+            total: 3,
+            running: BTreeMap::new(),
+            results: Vec::new(),
+            order: Vec::new(),
+            updates,
+            cancel: Arc::new(AtomicBool::new(false)),
+            done: false,
+        };
+
+        for i in [2, 0, 1] {
+            sender
+                .send(Update::Started(i, format!("{}", i).into()))
+                .unwrap();
+        }
+        sender.send(Update::Finished(2, result("c"))).unwrap();
+        sender.send(Update::Finished(0, result("a"))).unwrap();
+        assert!(!batch.poll());
+        assert_eq!(batch.running.keys().collect::<Vec<_>>(), [&1]);
+        assert_eq!(names(&batch.results), ["c", "a"]);
+        assert!((batch.fraction() - 2.0 / 3.0).abs() < 1e-6);
+
+        sender.send(Update::Finished(1, result("b"))).unwrap();
+        drop(sender);
+        assert!(batch.poll());
+        assert!(batch.running.is_empty());
+        assert_eq!(names(&batch.into_results()), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn wait_returns_every_file_in_order() {
+        let files: Vec<PathBuf> = (0..8)
+            .map(|i| std::env::temp_dir().join(format!("oxipng-gui-missing-{}.png", i)))
+            .collect();
+        let job = Job {
+            options: oxipng::Options::from_preset(0),
+            quantize: None,
+            preserve_attrs: false,
+            verify: false,
+            originals: Default::default(),
+        };
+
+        let results = Batch::start(
+            files.clone(),
+            std::env::temp_dir(),
+            OutputSettings::default(),
+            job,
+            || {},
+        )
+        .wait();
+        assert_eq!(
+            results.iter().map(|r| r.path.clone()).collect::<Vec<_>>(),
+            files
+        );
+        assert!(results.iter().all(|r| r.error.is_some()));
+    }
+}
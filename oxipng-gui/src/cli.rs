@@ -0,0 +1,472 @@
+use crate::batch::Batch;
+use crate::convert::Originals;
+use crate::input;
+use crate::optimize::{self, FileResult, Job};
+use crate::output::{OutputMode, OutputSettings};
+use crate::quantize::Quantize;
+use crate::settings::{Deflater, InterlaceMode, Settings, StripMode};
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+const USAGE: &str = "\
+Usage: oxipng-gui [options] <file or folder>...
+
+Options:
+      --preset <0-6>        Optimization preset, default 2
+      --strip <mode>        Strip metadata: none (default), safe, all
+      --keep <chunks>       Strip all chunks except these, e.g. iCCP,sRGB
+      --interlace <mode>    keep, off (default) or adam7
+      --zopfli <iterations> Compress with Zopfli instead of libdeflate
+      --timeout <seconds>   Per-file time limit
+      --alpha               Allow changing colors of fully transparent pixels
+      --verify              Compare pixels after optimizing and restore the
+                            original on any difference
+      --quantize            Reduce to a palette before lossless optimization
+      --quality <min>-<max> Quantization quality as SSIM x 100, default 70-95
+      --dither <0-1>        Dithering strength, default 1
+      --colors <2-256>      Maximum palette size, default 256
+                            The last three options imply --quantize
+      --out-dir <folder>    Write results to this folder, mirroring subfolders
+      --suffix <suffix>     Write results next to the originals, e.g. .min.png
+      --backup              Copy originals to a .bak folder before overwriting
+  -r, --recursive           Walk subfolders of the given folders
+      --exclude <pattern>   Skip matching files, may be repeated, e.g. *.min.png
+      --convert             Also convert BMP, TIFF, WebP, GIF and JPEG to PNG
+      --originals <mode>    After converting: keep (default), backup or trash
+      --json                Print a JSON report instead of text
+  -h, --help                Show this help
+
+Exits with status 1 if any file fails, 2 on invalid arguments.";
+
+#[derive(Debug, Default)]
+struct CliOptions {
+    preset: Option<u8>,
+    settings: Settings,
+    output: OutputSettings,
+    quantize: Option<Quantize>,
+    verify: bool,
+    recursive: bool,
+    excludes: Vec<String>,
+    convert: bool,
+    originals: Originals,
+    json: bool,
+    inputs: Vec<PathBuf>,
+    help: bool,
+}
+
+// 路径以外的参数必须是有效的 UTF-8 文本
+fn text(value: OsString) -> Result<String, String> {
+    value
+        .into_string()
+        .map_err(|v| format!("Invalid text argument: {}", v.to_string_lossy()))
+}
+
+fn parse(args: &[OsString]) -> Result<CliOptions, String> {
+    let mut opts = CliOptions::default();
+    let mut iter = args.iter();
+
+    // 路径参数保持 OsString，不要求是 UTF-8
+    while let Some(raw) = iter.next() {
+        let arg = raw.to_string_lossy();
+        let mut value = |name: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("Missing value for {}", name))
+        };
+
+        match arg.as_ref() {
+            "--preset" => {
+                let level = text(value(&arg)?)?;
+                let preset = level
+                    .parse()
+                    .ok()
+                    .filter(|p| *p <= 6)
+                    .ok_or_else(|| format!("Invalid preset: {}", level))?;
+                opts.preset = Some(preset);
+            }
+            "--strip" => {
+                let name = text(value(&arg)?)?;
+                opts.settings.strip = StripMode::from_name(&name)
+                    .filter(|m| *m != StripMode::Keep)
+                    .ok_or_else(|| format!("Invalid strip mode: {}", name))?;
+            }
+            "--keep" => {
+                opts.settings.strip = StripMode::Keep;
+                opts.settings.keep_chunks = text(value(&arg)?)?;
+            }
+            "--interlace" => {
+                let name = text(value(&arg)?)?;
+                opts.settings.interlace = InterlaceMode::from_name(&name)
+                    .ok_or_else(|| format!("Invalid interlace mode: {}", name))?;
+            }
+            "--zopfli" => {
+                let count = text(value(&arg)?)?;
+                let iterations = count
+                    .parse()
+                    .ok()
+                    .filter(|i| *i > 0)
+                    .ok_or_else(|| format!("Invalid iteration count: {}", count))?;
+                opts.settings.deflater = Deflater::Zopfli(iterations);
+            }
+            "--timeout" => {
+                let secs = text(value(&arg)?)?;
+                opts.settings.timeout_secs = secs
+                    .parse()
+                    .map_err(|_| format!("Invalid timeout: {}", secs))?;
+            }
+            "--alpha" => opts.settings.optimize_alpha = true,
+            "--verify" => opts.verify = true,
+            "--quantize" => {
+                opts.quantize.get_or_insert_with(Quantize::default);
+            }
+            "--quality" => {
+                let range = text(value(&arg)?)?;
+                let (min, max) = range
+                    .split_once('-')
+                    .and_then(|(min, max)| Some((min.parse().ok()?, max.parse().ok()?)))
+                    .ok_or_else(|| format!("Invalid quality range: {}", range))?;
+                let quantize = opts.quantize.get_or_insert_with(Quantize::default);
+                quantize.quality_min = min;
+                quantize.quality_max = max;
+            }
+            "--dither" => {
+                let level = text(value(&arg)?)?;
+                opts.quantize
+                    .get_or_insert_with(Quantize::default)
+                    .dithering = level
+                    .parse()
+                    .map_err(|_| format!("Invalid dithering level: {}", level))?;
+            }
+            "--colors" => {
+                let count = text(value(&arg)?)?;
+                opts.quantize
+                    .get_or_insert_with(Quantize::default)
+                    .max_colors = count
+                    .parse()
+                    .map_err(|_| format!("Invalid color count: {}", count))?;
+            }
+            "--out-dir" => {
+                opts.output.mode = OutputMode::Directory;
+                opts.output.directory = Some(value(&arg)?.into());
+            }
+            "--suffix" => {
+                opts.output.mode = OutputMode::Suffix;
+                opts.output.suffix = text(value(&arg)?)?;
+            }
+            "--backup" => opts.output.mode = OutputMode::Backup,
+            "-r" | "--recursive" => opts.recursive = true,
+            "--exclude" => opts
+                .excludes
+                .extend(input::parse_excludes(&text(value(&arg)?)?)),
+            "--convert" => opts.convert = true,
+            "--originals" => {
+                let name = text(value(&arg)?)?;
+                opts.originals = Originals::from_name(&name)
+                    .ok_or_else(|| format!("Invalid originals mode: {}", name))?;
+            }
+            "--json" => opts.json = true,
+            "-h" | "--help" => opts.help = true,
+            other if other.starts_with('-') => {
+                return Err(format!("Unknown option: {}", other));
+            }
+            _ => opts.inputs.push(raw.into()),
+        }
+    }
+
+    Ok(opts)
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{:.4}", value)
+    } else {
+        "null".to_string()
+    }
+}
+
+fn json_report(results: &[FileResult]) -> String {
+    let files: Vec<String> = results
+        .iter()
+        .map(|r| {
+            format!(
+                "    {{\"path\": {}, \"output\": {}, \"original\": {}, \"optimized\": {}, \"saved\": {}, \"elapsed_ms\": {}, \"quality\": {}, \"verified\": {}, \"error\": {}}}",
+                json_string(&r.path.display().to_string()),
+                json_string(&r.output.display().to_string()),
+                r.original,
+                r.optimized,
+                r.saved(),
+                r.elapsed.as_millis(),
+                r.quality.map_or("null".to_string(), |q| format!(
+                    "{{\"colors\": {}, \"psnr\": {}, \"ssim\": {}, \"applied\": {}}}",
+                    q.colors,
+                    json_number(q.psnr),
+                    json_number(q.ssim),
+                    q.applied
+                )),
+                r.verified,
+                r.error.as_deref().map_or("null".to_string(), json_string)
+            )
+        })
+        .collect();
+    let totals = optimize::totals(results);
+    format!(
+        "{{\n  \"files\": [\n{}\n  ],\n  \"totals\": {{\"files\": {}, \"failed\": {}, \"original\": {}, \"optimized\": {}, \"saved\": {}, \"elapsed_ms\": {}}}\n}}",
+        files.join(",\n"),
+        totals.files,
+        totals.failed,
+        totals.original,
+        totals.optimized,
+        totals.saved(),
+        totals.elapsed.as_millis()
+    )
+}
+
+fn text_report(results: &[FileResult]) {
+    for r in results {
+        match &r.error {
+            Some(e) => eprintln!("{}: error: {}", r.path.display(), e),
+            None => println!(
+                "{}: {} -> {} ({:.1}% saved){}",
+                r.path.display(),
+                optimize::format_size(r.original),
+                optimize::format_size(r.optimized),
+                r.saved_percent(),
+                match &r.quality {
+                    Some(q) if q.applied => format!(" [{}]", q.describe()),
+                    Some(q) => format!(" [kept lossless: {}]", q.describe()),
+                    None if r.verified => " [pixel-exact]".to_string(),
+                    None => String::new(),
+                }
+            ),
+        }
+    }
+    let totals = optimize::totals(results);
+    println!(
+        "{} file(s), {} failed, saved {} ({:.1}%) in {:.2}s",
+        totals.files,
+        totals.failed,
+        optimize::format_size(totals.saved()),
+        totals.saved_percent(),
+        totals.elapsed.as_secs_f64()
+    );
+}
+
+// 以 windows 子系统构建时进程没有控制台，命令行模式下附加到启动它的控制台以便输出
+#[cfg(target_os = "windows")]
+pub fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // 从资源管理器启动时没有父控制台，调用失败可以忽略
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn attach_console() {}
+
+// 返回进程退出码
+pub fn run(args: &[OsString]) -> i32 {
+    let opts = match parse(args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+    if opts.help {
+        println!("{}", USAGE);
+        return 0;
+    }
+    if opts.inputs.is_empty() {
+        eprintln!("No input files\n\n{}", USAGE);
+        return 2;
+    }
+
+    // 与界面使用同一套选项构建
+    let options = match opts.settings.to_options(opts.preset.unwrap_or(2)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    if let Err(e) = opts.output.validate() {
+        eprintln!("{}", e);
+        return 2;
+    }
+    if let Some(Err(e)) = opts.quantize.as_ref().map(Quantize::validate) {
+        eprintln!("{}", e);
+        return 2;
+    }
+    if let Some(missing) = opts.inputs.iter().find(|p| !p.exists()) {
+        eprintln!("Not found: {}", missing.display());
+        return 1;
+    }
+
+    let files = input::collect(
+        &opts.inputs,
+        opts.recursive,
+        &opts.excludes,
+        opts.convert,
+        opts.output.output_dir(),
+    );
+    if files.is_empty() {
+        eprintln!("No image files found");
+        return 1;
+    }
+    let root = input::root(&opts.inputs);
+    let job = Job {
+        options,
+        quantize: opts.quantize,
+        preserve_attrs: opts.output.preserve_attrs,
+        verify: opts.verify,
+        originals: opts.originals,
+    };
+    let results = Batch::start(files, root, opts.output, job, || {}).wait();
+
+    if opts.json {
+        println!("{}", json_report(&results));
+    } else {
+        text_report(&results);
+    }
+    if optimize::totals(&results).failed > 0 {
+        1
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn invalid_arguments_exit_with_2() {
+        assert_eq!(run(&args(&["--preset", "7", "a.png"])), 2);
+        assert_eq!(run(&args(&["--preset", "x", "a.png"])), 2);
+        assert_eq!(run(&args(&["--bogus", "a.png"])), 2);
+        assert_eq!(run(&args(&["--preset"])), 2);
+        assert_eq!(run(&args(&["--json"])), 2);
+        assert_eq!(
+            parse(&args(&["--frobnicate"])).unwrap_err(),
+            "Unknown option: --frobnicate"
+        );
+    }
+
+    #[test]
+    fn quantize_options_imply_quantize() {
+        assert!(parse(&args(&["a.png"])).unwrap().quantize.is_none());
+        assert_eq!(
+            parse(&args(&["--quantize"])).unwrap().quantize,
+            Some(Quantize::default())
+        );
+
+        let quantize = parse(&args(&["--quality", "60-80"]))
+            .unwrap()
+            .quantize
+            .unwrap();
+        assert_eq!((quantize.quality_min, quantize.quality_max), (60, 80));
+        let quantize = parse(&args(&["--dither", "0.5"]))
+            .unwrap()
+            .quantize
+            .unwrap();
+        assert_eq!(quantize.dithering, 0.5);
+        let quantize = parse(&args(&["--colors", "16", "--quantize"]))
+            .unwrap()
+            .quantize
+            .unwrap();
+        assert_eq!(quantize.max_colors, 16);
+
+        assert!(parse(&args(&["--quality", "80"])).is_err());
+    }
+
+    #[test]
+    fn output_options_set_mode() {
+        let opts = parse(&args(&["--out-dir", "out", "-r", "in"])).unwrap();
+        assert_eq!(opts.output.mode, OutputMode::Directory);
+        assert_eq!(opts.output.directory, Some(PathBuf::from("out")));
+        assert!(opts.recursive);
+        assert_eq!(opts.inputs, [PathBuf::from("in")]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_non_utf8_paths() {
+        use std::os::unix::ffi::OsStringExt;
+        let path = OsString::from_vec(b"caf\xe9.png".to_vec());
+
+        let opts = parse(std::slice::from_ref(&path)).unwrap();
+        assert_eq!(opts.inputs, [PathBuf::from(&path)]);
+        assert!(parse(&[OsString::from("--suffix"), path]).is_err());
+    }
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(
+            json_string("say \"hi\"\\ C:\\dir"),
+            "\"say \\\"hi\\\"\\\\ C:\\\\dir\""
+        );
+        assert_eq!(json_string("a\nb\tc\r"), "\"a\\nb\\tc\\r\"");
+        assert_eq!(json_string("\u{1}\u{1f}"), "\"\\u0001\\u001f\"");
+        assert_eq!(json_string("é 图"), "\"é 图\"");
+    }
+
+    #[test]
+    fn non_finite_numbers_are_null() {
+        assert_eq!(json_number(0.5), "0.5000");
+        assert_eq!(json_number(f64::INFINITY), "null");
+        assert_eq!(json_number(f64::NAN), "null");
+    }
+
+    #[test]
+    fn lossless_quantization_reports_null_psnr() {
+        let pixels = [[1, 2, 3, 255], [4, 5, 6, 255]];
+        let result = FileResult {
+            original: 100,
+            optimized: 60,
+            quality: Some(crate::quantize::Quality {
+                colors: 2,
+                psnr: crate::quantize::psnr(&pixels, &pixels),
+                ssim: crate::quantize::ssim(&pixels, &pixels, 2),
+                applied: true,
+            }),
+            ..FileResult::new("a.png", "a.png")
+        };
+
+        let report = json_report(&[result]);
+        assert!(
+            report.contains(
+                "\"quality\": {\"colors\": 2, \"psnr\": null, \"ssim\": 1.0000, \"applied\": true}"
+            ),
+            "{}",
+            report
+        );
+    }
+}
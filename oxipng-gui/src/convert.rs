@@ -0,0 +1,176 @@
+use crate::output::BACKUP_DIR;
+use std::io::Cursor;
+use std::path::Path;
+
+// 可解码并转换为 PNG 的其他格式；GIF 只取第一帧
+pub const EXTENSIONS: [&str; 7] = ["bmp", "tif", "tiff", "webp", "gif", "jpg", "jpeg"];
+
+pub fn is_convertible(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
+
+// 转换成功后如何处理原文件
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Originals {
+    #[default]
+    Keep,
+    // 移动到同级的 .bak 目录
+    Backup,
+    // 移至回收站
+    Trash,
+}
+
+impl Originals {
+    pub const ALL: [Originals; 3] = [Originals::Keep, Originals::Backup, Originals::Trash];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Originals::Keep => "keep",
+            Originals::Backup => "backup",
+            Originals::Trash => "trash",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|o| o.name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Originals::Keep => "Keep originals",
+            Originals::Backup => "Move originals to .bak",
+            Originals::Trash => "Move originals to trash",
+        }
+    }
+}
+
+// 按内容识别格式并解码，编码为未优化的 PNG
+pub fn to_png(path: &Path) -> Result<Vec<u8>, String> {
+    let image = image::ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())?
+        .decode()
+        .map_err(|e| e.to_string())?;
+    let mut data = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut data), image::ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(data)
+}
+
+pub fn dispose(path: &Path, originals: Originals) -> Result<(), String> {
+    match originals {
+        Originals::Keep => Ok(()),
+        Originals::Backup => {
+            let name = path.file_name().unwrap_or_default();
+            let dir = path.parent().unwrap_or(Path::new("")).join(BACKUP_DIR);
+            std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+            std::fs::rename(path, dir.join(name)).map_err(|e| e.to_string())
+        }
+        Originals::Trash => trash::delete(path).map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // 测试用的临时目录，结束时删除
+    struct Dir(PathBuf);
+
+    impl Dir {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!(
+                "oxipng-gui-convert-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(&root).unwrap();
+            Self(root)
+        }
+    }
+
+    impl Drop for Dir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn sample() -> image::RgbaImage {
+        image::RgbaImage::from_fn(5, 3, |x, y| {
+            image::Rgba([(x * 50) as u8, (y * 80) as u8, 200, 255])
+        })
+    }
+
+    fn encode(image: &image::RgbaImage, format: image::ImageFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), format).unwrap();
+        data
+    }
+
+    #[test]
+    fn recognizes_other_formats_ignoring_case() {
+        assert!(is_convertible(Path::new("a.bmp")));
+        assert!(is_convertible(Path::new("dir/a.JPEG")));
+        assert!(is_convertible(Path::new("a.WebP")));
+        assert!(!is_convertible(Path::new("a.png")));
+        assert!(!is_convertible(Path::new("a.txt")));
+        assert!(!is_convertible(Path::new("bmp")));
+    }
+
+    #[test]
+    fn originals_round_trip_through_names() {
+        for originals in Originals::ALL {
+            assert_eq!(Originals::from_name(originals.name()), Some(originals));
+        }
+        assert_eq!(Originals::from_name("delete"), None);
+    }
+
+    #[test]
+    fn converts_to_png_with_identical_pixels() {
+        let dir = Dir::new("to-png");
+        let image = sample();
+        for (name, format) in [
+            ("a.bmp", image::ImageFormat::Bmp),
+            ("a.webp", image::ImageFormat::WebP),
+        ] {
+            let path = dir.0.join(name);
+            std::fs::write(&path, encode(&image, format)).unwrap();
+
+            let png = to_png(&path).unwrap();
+            let decoded = image::load_from_memory_with_format(&png, image::ImageFormat::Png)
+                .unwrap()
+                .to_rgba8();
+            assert_eq!(decoded, image, "{}", name);
+        }
+    }
+
+    #[test]
+    fn undecodable_file_is_an_error() {
+        let dir = Dir::new("invalid");
+        let path = dir.0.join("a.bmp");
+        std::fs::write(&path, b"not an image").unwrap();
+        assert!(to_png(&path).is_err());
+    }
+
+    #[test]
+    fn backup_moves_original_into_bak() {
+        let dir = Dir::new("backup");
+        let path = dir.0.join("a.bmp");
+        std::fs::write(&path, b"original").unwrap();
+
+        dispose(&path, Originals::Keep).unwrap();
+        assert!(path.exists());
+
+        dispose(&path, Originals::Backup).unwrap();
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read(dir.0.join(BACKUP_DIR).join("a.bmp")).unwrap(),
+            b"original"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use crate::convert;
+use crate::output::{self, BACKUP_DIR};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+pub fn is_png(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+}
+
+// convert 时同时接受可转换为 PNG 的其他格式
+fn accepts(path: &Path, convert: bool) -> bool {
+    is_png(path) || (convert && convert::is_convertible(path))
+}
+
+// 逗号分隔的排除规则，支持 * 与 ?，匹配文件名或相对所选目录的路径
+pub fn parse_excludes(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(|p| p.replace('\\', "/"))
+        .collect()
+}
+
+// 不区分 ASCII 大小写，* 可跨越 '/'
+fn wildcard(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // 上一个 * 的位置及其当前匹配到的文本位置
+    let mut star = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p].eq_ignore_ascii_case(&text[t])) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+fn excluded(path: &Path, base: &Path, excludes: &[String]) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let relative = path
+        .strip_prefix(base)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/");
+    excludes.iter().any(|pattern| {
+        wildcard(pattern.as_bytes(), name.as_bytes())
+            || wildcard(pattern.as_bytes(), relative.as_bytes())
+    })
+}
+
+struct Walk<'a> {
+    recursive: bool,
+    convert: bool,
+    excludes: &'a [String],
+    // 规范化后的输出目录，位于所选目录内时不再读取其中的结果
+    skip: Option<PathBuf>,
+}
+
+impl Walk<'_> {
+    fn skipped(&self, dir: &Path) -> bool {
+        dir.file_name().is_some_and(|n| n == BACKUP_DIR)
+            || self
+                .skip
+                .as_ref()
+                .is_some_and(|skip| dir.canonicalize().is_ok_and(|d| &d == skip))
+    }
+}
+
+// 目录下的图片文件，按名称排序，跳过备份目录与输出目录。
+// 不跟随指向目录的符号链接，避免循环链接导致无限递归
+fn walk(dir: &Path, base: &Path, options: &Walk, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<(PathBuf, bool)> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| (e.path(), e.file_type().is_ok_and(|t| t.is_dir())))
+        .collect();
+    entries.sort();
+    for (path, is_dir) in entries {
+        if excluded(&path, base, options.excludes) {
+            continue;
+        }
+        if is_dir {
+            if options.recursive && !options.skipped(&path) {
+                walk(&path, base, options, files);
+            }
+        } else if accepts(&path, options.convert) && path.is_file() {
+            files.push(path);
+        }
+    }
+}
+
+// 把所选的文件与目录展开为待处理的文件列表，去除重复。
+// output_dir 为输出目录，其中已有的结果不会再次作为输入
+pub fn collect(
+    inputs: &[PathBuf],
+    recursive: bool,
+    excludes: &[String],
+    convert: bool,
+    output_dir: Option<&Path>,
+) -> Vec<PathBuf> {
+    let options = Walk {
+        recursive,
+        convert,
+        excludes,
+        skip: output_dir.and_then(|dir| dir.canonicalize().ok()),
+    };
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            walk(input, input, &options, &mut files);
+        } else if accepts(input, convert) {
+            let base = input.parent().unwrap_or(Path::new(""));
+            if !excluded(input, base, excludes) {
+                files.push(input.clone());
+            }
+        }
+    }
+    let mut seen = HashSet::new();
+    files.retain(|f| seen.insert(f.clone()));
+    files
+}
+
+// 输出目录镜像时的基准目录：所选目录本身或所选文件的父目录的公共前缀
+pub fn root(inputs: &[PathBuf]) -> PathBuf {
+    output::common_root(inputs.iter().filter_map(|p| {
+        if p.is_dir() {
+            Some(p.as_path())
+        } else {
+            p.parent()
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        wildcard(pattern.as_bytes(), text.as_bytes())
+    }
+
+    // 测试用的临时目录树，结束时删除
+    struct Tree(PathBuf);
+
+    impl Tree {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let root =
+                std::env::temp_dir().join(format!("oxipng-gui-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&root);
+            for file in files {
+                let path = root.join(file);
+                std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+                std::fs::write(path, b"").unwrap();
+            }
+            Self(root)
+        }
+
+        fn relative(&self, files: &[PathBuf]) -> Vec<String> {
+            files
+                .iter()
+                .map(|f| {
+                    f.strip_prefix(&self.0)
+                        .unwrap()
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                })
+                .collect()
+        }
+    }
+
+    impl Drop for Tree {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn wildcard_matches_star_and_question_mark() {
+        assert!(matches("*.min.png", "a.min.png"));
+        assert!(!matches("*.min.png", "a.png"));
+        assert!(matches("icon-??.png", "icon-32.png"));
+        assert!(!matches("icon-??.png", "icon-128.png"));
+        assert!(matches("*", ""));
+        assert!(matches("a*b*c", "aXbYbZc"));
+        assert!(!matches("a*b*c", "aXbYbZ"));
+    }
+
+    #[test]
+    fn wildcard_ignores_ascii_case() {
+        assert!(matches("*.PNG", "photo.png"));
+        assert!(matches("Thumbs/*", "thumbs/a.png"));
+        assert!(!matches("*.png", "photo.pngx"));
+    }
+
+    #[test]
+    fn parses_exclude_list() {
+        assert_eq!(
+            parse_excludes(" *.min.png , ,thumbs\\*,"),
+            ["*.min.png", "thumbs/*"]
+        );
+        assert!(parse_excludes("").is_empty());
+    }
+
+    #[test]
+    fn folder_patterns_match_relative_paths() {
+        let base = Path::new("/images");
+        let excludes = parse_excludes("thumbs/*, cache");
+        assert!(excluded(Path::new("/images/thumbs/a.png"), base, &excludes));
+        // * 跨越 '/'，子目录中的文件也被排除
+        assert!(excluded(
+            Path::new("/images/thumbs/x/a.png"),
+            base,
+            &excludes
+        ));
+        assert!(excluded(Path::new("/images/cache"), base, &excludes));
+        assert!(!excluded(
+            Path::new("/images/a/thumbs.png"),
+            base,
+            &excludes
+        ));
+    }
+
+    #[test]
+    fn collects_sorted_images_without_excluded_and_backup() {
+        let tree = Tree::new(
+            "collect",
+            &[
+                "b.png",
+                "a.PNG",
+                "a.min.png",
+                "notes.txt",
+                "sub/c.png",
+                "thumbs/d.png",
+                ".bak/a.png",
+            ],
+        );
+        let excludes = parse_excludes("*.min.png, thumbs");
+
+        let files = collect(std::slice::from_ref(&tree.0), true, &excludes, false, None);
+        assert_eq!(tree.relative(&files), ["a.PNG", "b.png", "sub/c.png"]);
+
+        let files = collect(
+            &[tree.0.clone(), tree.0.join("b.png")],
+            false,
+            &excludes,
+            false,
+            None,
+        );
+        assert_eq!(tree.relative(&files), ["a.PNG", "b.png"]);
+    }
+
+    #[test]
+    fn skips_output_directory_inside_input() {
+        let tree = Tree::new("output-dir", &["a.png", "out/a.png", "sub/out/b.png"]);
+
+        let files = collect(
+            std::slice::from_ref(&tree.0),
+            true,
+            &[],
+            false,
+            Some(&tree.0.join("out")),
+        );
+        assert_eq!(tree.relative(&files), ["a.png", "sub/out/b.png"]);
+    }
+
+    #[test]
+    fn collects_other_formats_only_when_converting() {
+        let tree = Tree::new(
+            "convert",
+            &["a.png", "b.BMP", "c.webp", "notes.txt", "sub/d.jpg"],
+        );
+
+        let files = collect(std::slice::from_ref(&tree.0), true, &[], true, None);
+        assert_eq!(
+            tree.relative(&files),
+            ["a.png", "b.BMP", "c.webp", "sub/d.jpg"]
+        );
+
+        let files = collect(std::slice::from_ref(&tree.0), true, &[], false, None);
+        assert_eq!(tree.relative(&files), ["a.png"]);
+
+        // 直接选择的文件同样遵循 convert
+        let bmp = [tree.0.join("b.BMP")];
+        assert_eq!(collect(&bmp, false, &[], true, None), bmp);
+        assert!(collect(&bmp, false, &[], false, None).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn does_not_follow_directory_symlinks() {
+        let tree = Tree::new("symlink", &["a.png", "sub/b.png"]);
+        std::os::unix::fs::symlink(&tree.0, tree.0.join("sub/loop")).unwrap();
+        std::os::unix::fs::symlink(tree.0.join("a.png"), tree.0.join("link.png")).unwrap();
+
+        let files = collect(std::slice::from_ref(&tree.0), true, &[], false, None);
+        assert_eq!(tree.relative(&files), ["a.png", "link.png", "sub/b.png"]);
+    }
+}
@@ -1,14 +1,123 @@
 #![cfg_attr(target_os = "windows", windows_subsystem = "windows")]
+mod batch;
+mod cli;
+mod convert;
+mod input;
+mod optimize;
+mod output;
+mod quantize;
+mod settings;
+mod verify;
+
+use batch::Batch;
+use convert::Originals;
 use eframe::egui;
-use rayon::prelude::*;
+use optimize::{FileResult, Job};
+use output::{OutputMode, OutputSettings};
+use quantize::Quantize;
 use rfd::FileDialog;
-use std::path::PathBuf;
+use settings::{Deflater, InterlaceMode, Settings, StripMode};
+use std::path::{Path, PathBuf};
+
+// 点击结果表中的文件后显示的前后对比
+struct Comparison {
+    path: PathBuf,
+    // 原图已被覆盖且没有备份时为 None
+    original: Option<Result<egui::TextureHandle, String>>,
+    optimized: Result<egui::TextureHandle, String>,
+    zoom: f32,
+    // 滑动分割对比，否则左右并排
+    wipe: bool,
+    // 分割线位置，0 为最左
+    split: f32,
+}
+
+impl Comparison {
+    fn size(&self) -> egui::Vec2 {
+        [self.original.as_ref(), Some(&self.optimized)]
+            .into_iter()
+            .flatten()
+            .find_map(|t| t.as_ref().ok())
+            .map_or(egui::Vec2::ZERO, |t| t.size_vec2())
+    }
+}
+
+fn load_texture(
+    ctx: &egui::Context,
+    name: &str,
+    path: &Path,
+) -> Result<egui::TextureHandle, String> {
+    let decoded = quantize::decode(path)?;
+    let (width, height) = (decoded.width as usize, decoded.height as usize);
+    let max = ctx.input(|i| i.max_texture_side);
+    if width > max || height > max {
+        return Err(format!("Image too large to preview ({}x{})", width, height));
+    }
+    let image =
+        egui::ColorImage::from_rgba_unmultiplied([width, height], decoded.pixels.as_flattened());
+    // 放大时保持像素边缘清晰
+    Ok(ctx.load_texture(name, image, egui::TextureOptions::NEAREST))
+}
+
+// 棋盘格背景，便于看出透明区域
+fn checkerboard(painter: &egui::Painter, rect: egui::Rect) {
+    const CELL: f32 = 8.0;
+    painter.rect_filled(rect, 0.0, egui::Color32::from_gray(204));
+    let visible = rect.intersect(painter.clip_rect());
+    if !visible.is_positive() {
+        return;
+    }
+    let first_col = ((visible.left() - rect.left()) / CELL).floor() as i32;
+    let last_col = ((visible.right() - rect.left()) / CELL).ceil() as i32;
+    let first_row = ((visible.top() - rect.top()) / CELL).floor() as i32;
+    let last_row = ((visible.bottom() - rect.top()) / CELL).ceil() as i32;
+    for row in first_row..last_row {
+        for col in first_col..last_col {
+            if (row + col) % 2 == 0 {
+                continue;
+            }
+            let min = rect.min + egui::vec2(col as f32 * CELL, row as f32 * CELL);
+            let cell = egui::Rect::from_min_size(min, egui::vec2(CELL, CELL)).intersect(rect);
+            painter.rect_filled(cell, 0.0, egui::Color32::from_gray(153));
+        }
+    }
+}
+
+fn paint_texture(painter: &egui::Painter, texture: &egui::TextureHandle, rect: egui::Rect) {
+    let uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+    painter.image(texture.id(), rect, uv, egui::Color32::WHITE);
+}
 
 #[derive(Default)]
 struct PngCompress {
+    // 所选的文件与目录
+    inputs: Vec<PathBuf>,
+    // 是否递归进入子目录
+    recursive: bool,
+    // 逗号分隔的排除规则
+    excludes: String,
+    // 是否将其他格式转换为 PNG，以及转换后如何处理原文件
+    convert: bool,
+    originals: Originals,
+    // 展开后的待处理文件
     image_path: Option<Vec<PathBuf>>,
     opt_lvl: u8,
+    // 高级选项，在预设基础上覆盖
+    settings: Settings,
+    // 输出位置
+    output: OutputSettings,
+    // 是否在无损优化前进行有损量化
+    lossy: bool,
+    quantize: Quantize,
+    // 无损优化后逐像素校验
+    verify: bool,
     status_message: String,
+    // 上次处理的逐文件结果
+    results: Vec<FileResult>,
+    // 正在后台运行的批处理
+    batch: Option<Batch>,
+    // 前后对比窗口
+    comparison: Option<Comparison>,
 }
 
 impl PngCompress {
@@ -58,103 +167,793 @@ impl PngCompress {
     }
 
     fn clear_state(&mut self) {
+        self.inputs.clear();
         self.image_path = None;
+        self.results.clear();
+        self.comparison = None;
     }
 
-    fn execute_oxipng(&mut self) {
-        if let Some(image) = &self.image_path {
-            image.par_iter().for_each(|path| {
-                let _ = oxipng::optimize(
-                    &oxipng::InFile::Path(path.to_path_buf()),
-                    &oxipng::OutFile::Path {
-                        path: None,
-                        preserve_attrs: true,
-                    },
-                    &oxipng::Options::from_preset(2),
-                );
+    fn set_inputs(&mut self, inputs: Vec<PathBuf>) {
+        self.inputs = inputs;
+        self.expand_inputs();
+    }
+
+    // 按当前的递归与排除设置重新展开输入
+    fn expand_inputs(&mut self) {
+        let excludes = input::parse_excludes(&self.excludes);
+        let files = input::collect(
+            &self.inputs,
+            self.recursive,
+            &excludes,
+            self.convert,
+            self.output.output_dir(),
+        );
+        self.status_message = format!(
+            "Found {} image file(s) in {} selected item(s)",
+            files.len(),
+            self.inputs.len()
+        );
+        self.image_path = (!files.is_empty()).then_some(files);
+    }
+
+    fn execute_oxipng(&mut self, ctx: &egui::Context) {
+        let Some(image) = &self.image_path else {
+            return;
+        };
+        if self.batch.is_some() {
+            return;
+        }
+        let options = match self.settings.to_options(self.opt_lvl) {
+            Ok(options) => options,
+            Err(e) => {
+                self.status_message = e;
+                return;
+            }
+        };
+        if let Err(e) = self.output.validate() {
+            self.status_message = e;
+            return;
+        }
+        let quantize = self.lossy.then(|| self.quantize.clone());
+        if let Some(Err(e)) = quantize.as_ref().map(Quantize::validate) {
+            self.status_message = e;
+            return;
+        }
+        let root = input::root(&self.inputs);
+        let ctx = ctx.clone();
+        self.results.clear();
+        self.comparison = None;
+        self.status_message.clear();
+        let job = Job {
+            options,
+            quantize,
+            preserve_attrs: self.output.preserve_attrs,
+            verify: self.verify,
+            originals: self.originals,
+        };
+        self.batch = Some(Batch::start(
+            image.clone(),
+            root,
+            self.output.clone(),
+            job,
+            move || ctx.request_repaint(),
+        ));
+    }
+
+    // 取回后台进度，批处理结束后汇总结果
+    fn poll_batch(&mut self) {
+        let Some(batch) = &mut self.batch else {
+            return;
+        };
+        if !batch.poll() {
+            return;
+        }
+        let Some(batch) = self.batch.take() else {
+            return;
+        };
+        let skipped = batch.total - batch.finished();
+        self.results = batch.into_results();
+
+        let totals = optimize::totals(&self.results);
+        let mut message = if totals.failed == 0 {
+            format!(
+                "Optimized {} file(s), saved {} ({:.1}%)",
+                totals.files,
+                optimize::format_size(totals.saved()),
+                totals.saved_percent()
+            )
+        } else {
+            format!(
+                "Optimized {} file(s), {} failed, saved {} ({:.1}%)",
+                totals.files - totals.failed,
+                totals.failed,
+                optimize::format_size(totals.saved()),
+                totals.saved_percent()
+            )
+        };
+        if skipped > 0 {
+            message = format!("Cancelled, {} file(s) skipped. {}", skipped, message);
+        }
+        self.status_message = message;
+    }
+
+    fn open_comparison(&mut self, ctx: &egui::Context, result: &FileResult) {
+        let original = result
+            .original_source()
+            .map(|path| load_texture(ctx, "comparison_original", path));
+        let optimized = load_texture(ctx, "comparison_optimized", &result.output);
+        self.comparison = Some(Comparison {
+            path: result.path.clone(),
+            wipe: original.as_ref().is_some_and(|o| o.is_ok()),
+            original,
+            optimized,
+            zoom: 1.0,
+            split: 0.5,
+        });
+    }
+
+    fn comparison_ui(&mut self, ctx: &egui::Context) {
+        let Some(comparison) = &mut self.comparison else {
+            return;
+        };
+        let mut open = true;
+        let title = format!(
+            "Preview: {}",
+            comparison
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+        );
+        egui::Window::new(title)
+            .id(egui::Id::new("comparison"))
+            .open(&mut open)
+            .default_size([720.0, 480.0])
+            .show(ctx, |ui| {
+                let can_wipe = comparison.optimized.is_ok()
+                    && comparison.original.as_ref().is_some_and(|o| o.is_ok());
+                let size = comparison.size();
+
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut comparison.wipe, false, "Side by side");
+                    ui.add_enabled_ui(can_wipe, |ui| {
+                        ui.radio_value(&mut comparison.wipe, true, "Wipe");
+                    });
+                    ui.separator();
+                    ui.add(
+                        egui::Slider::new(&mut comparison.zoom, 0.05..=32.0)
+                            .logarithmic(true)
+                            .text("Zoom"),
+                    );
+                    if ui.button("1:1").clicked() {
+                        comparison.zoom = 1.0;
+                    }
+                    if ui.button("Fit").clicked() && size.x > 0.0 {
+                        let columns = if comparison.wipe { 1.0 } else { 2.0 };
+                        let available = ui.available_size() - egui::vec2(20.0, 60.0);
+                        comparison.zoom = (available.x / (size.x * columns))
+                            .min(available.y / size.y)
+                            .clamp(0.05, 32.0);
+                    }
+                });
+                if comparison.original.is_none() {
+                    ui.weak(
+                        "The original was overwritten. Use an output folder, suffix or backup to compare.",
+                    );
+                }
+                ui.weak("Ctrl + scroll to zoom");
+
+                let scroll = egui::ScrollArea::both().show(ui, |ui| {
+                    let zoom = comparison.zoom;
+                    if comparison.wipe && can_wipe {
+                        let (Some(Ok(original)), Ok(optimized)) =
+                            (&comparison.original, &comparison.optimized)
+                        else {
+                            return;
+                        };
+                        ui.label("Original ◀ | ▶ Optimized  (drag to move the divider)");
+                        let (rect, response) =
+                            ui.allocate_exact_size(size * zoom, egui::Sense::click_and_drag());
+                        if response.dragged() || response.clicked() {
+                            if let Some(pos) = response.interact_pointer_pos() {
+                                comparison.split =
+                                    ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+                            }
+                        }
+                        let split_x = rect.left() + rect.width() * comparison.split;
+                        let painter = ui.painter();
+                        checkerboard(painter, rect);
+
+                        let mut left = rect;
+                        left.set_right(split_x);
+                        paint_texture(
+                            &painter.with_clip_rect(left.intersect(painter.clip_rect())),
+                            original,
+                            rect,
+                        );
+                        let mut right = rect;
+                        right.set_left(split_x);
+                        paint_texture(
+                            &painter.with_clip_rect(right.intersect(painter.clip_rect())),
+                            optimized,
+                            rect,
+                        );
+                        painter.vline(
+                            split_x,
+                            rect.y_range(),
+                            egui::Stroke::new(1.5, egui::Color32::YELLOW),
+                        );
+                    } else {
+                        ui.horizontal_top(|ui| {
+                            let sides = [
+                                ("Original", comparison.original.as_ref()),
+                                ("Optimized", Some(&comparison.optimized)),
+                            ];
+                            for (label, texture) in sides {
+                                let Some(texture) = texture else {
+                                    continue;
+                                };
+                                ui.vertical(|ui| {
+                                    ui.label(label);
+                                    match texture {
+                                        Ok(texture) => {
+                                            let (rect, _) = ui.allocate_exact_size(
+                                                texture.size_vec2() * zoom,
+                                                egui::Sense::hover(),
+                                            );
+                                            checkerboard(ui.painter(), rect);
+                                            paint_texture(ui.painter(), texture, rect);
+                                        }
+                                        Err(e) => {
+                                            ui.colored_label(egui::Color32::LIGHT_RED, e);
+                                        }
+                                    }
+                                });
+                            }
+                        });
+                    }
+                });
+
+                // 鼠标位于图像区域时按住 Ctrl 滚动缩放
+                if ui.rect_contains_pointer(scroll.inner_rect) {
+                    let delta = ui.input(|i| i.zoom_delta());
+                    if delta != 1.0 {
+                        comparison.zoom = (comparison.zoom * delta).clamp(0.05, 32.0);
+                    }
+                }
             });
+        if !open {
+            self.comparison = None;
+        }
+    }
+
+    fn progress_ui(&self, ui: &mut egui::Ui, batch: &Batch) {
+        let totals = optimize::totals(&batch.results);
+        ui.horizontal(|ui| {
+            if batch.is_cancelled() {
+                ui.add_enabled(false, egui::Button::new("Cancelling..."));
+            } else if ui
+                .button("Cancel")
+                .on_hover_text(
+                    "Skips files that have not started yet.\n\
+                     Files already being optimized run to completion; \
+                     set a Timeout under Advanced options to bound them.",
+                )
+                .clicked()
+            {
+                batch.cancel();
+            }
+            ui.add(
+                egui::ProgressBar::new(batch.fraction())
+                    .text(format!(
+                        "{}/{} files, saved {}",
+                        batch.finished(),
+                        batch.total,
+                        optimize::format_size(totals.saved())
+                    ))
+                    .animate(true),
+            );
+        });
+        if batch.is_cancelled() && !batch.running.is_empty() {
+            // oxipng 无法中途停止，只能等待（受超时设置限制）
+            let limit = match self.settings.timeout_secs {
+                0 => "no timeout set".to_string(),
+                secs => format!("up to {}s each", secs),
+            };
+            ui.weak(format!(
+                "Waiting for {} file(s) in progress to finish ({})",
+                batch.running.len(),
+                limit
+            ));
         }
+    }
 
-        self.status_message = "Optimize Success!".to_string();
+    fn output_ui(&mut self, ui: &mut egui::Ui) {
+        let before = self.output.output_dir().map(Path::to_path_buf);
+        let output = &mut self.output;
+        egui::CollapsingHeader::new("Output").show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.radio_value(&mut output.mode, OutputMode::Overwrite, "Overwrite");
+                ui.radio_value(&mut output.mode, OutputMode::Directory, "Output folder");
+                ui.radio_value(&mut output.mode, OutputMode::Suffix, "Add suffix");
+                ui.radio_value(&mut output.mode, OutputMode::Backup, "Backup originals");
+            });
+            match output.mode {
+                OutputMode::Overwrite => {}
+                OutputMode::Directory => {
+                    ui.horizontal(|ui| {
+                        if ui.button("Choose folder").clicked() {
+                            if let Some(dir) = FileDialog::new().pick_folder() {
+                                output.directory = Some(dir);
+                            }
+                        }
+                        match &output.directory {
+                            Some(dir) => ui.label(dir.display().to_string()),
+                            None => ui.weak("No folder selected"),
+                        };
+                    });
+                    ui.weak("Subfolders are mirrored relative to the common folder of the inputs");
+                }
+                OutputMode::Suffix => {
+                    ui.horizontal(|ui| {
+                        ui.label("Suffix");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut output.suffix)
+                                .hint_text(".min.png")
+                                .desired_width(100.0),
+                        );
+                    });
+                }
+                OutputMode::Backup => {
+                    ui.weak(format!(
+                        "Originals are copied to a \"{}\" folder next to each file",
+                        output::BACKUP_DIR
+                    ));
+                }
+            }
+            ui.checkbox(&mut output.preserve_attrs, "Preserve file attributes");
+        });
+        // 输出目录可能位于所选目录内，变化时重新展开输入
+        if self.output.output_dir() != before.as_deref() && !self.inputs.is_empty() {
+            self.expand_inputs();
+        }
+    }
+
+    fn quantize_ui(&mut self, ui: &mut egui::Ui) {
+        let quantize = &mut self.quantize;
+        egui::CollapsingHeader::new("Lossy quantization").show(ui, |ui| {
+            ui.checkbox(&mut self.lossy, "Reduce to a palette before optimizing");
+            ui.add_enabled_ui(self.lossy, |ui| {
+                egui::Grid::new("quantize").num_columns(2).show(ui, |ui| {
+                    ui.label("Quality");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue::new(&mut quantize.quality_min)
+                                .range(0..=quantize.quality_max),
+                        );
+                        ui.label("to");
+                        ui.add(
+                            egui::DragValue::new(&mut quantize.quality_max)
+                                .range(quantize.quality_min..=100),
+                        );
+                    })
+                    .response
+                    .on_hover_text(
+                        "Quality is SSIM × 100. Below the minimum the file is kept lossless; \
+                         at or above the maximum fewer colors are tried.",
+                    );
+                    ui.end_row();
+
+                    ui.label("Dithering");
+                    ui.add(egui::Slider::new(&mut quantize.dithering, 0.0..=1.0));
+                    ui.end_row();
+
+                    ui.label("Max colors");
+                    ui.add(egui::Slider::new(&mut quantize.max_colors, 2..=256));
+                    ui.end_row();
+                });
+            });
+        });
+    }
+
+    fn advanced_ui(&mut self, ui: &mut egui::Ui) {
+        let settings = &mut self.settings;
+        let verify = &mut self.verify;
+        egui::CollapsingHeader::new("Advanced options").show(ui, |ui| {
+            egui::Grid::new("advanced").num_columns(2).show(ui, |ui| {
+                ui.label("Strip metadata");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut settings.strip, StripMode::None, "None");
+                    ui.radio_value(&mut settings.strip, StripMode::Safe, "Safe");
+                    ui.radio_value(&mut settings.strip, StripMode::All, "All");
+                    ui.radio_value(&mut settings.strip, StripMode::Keep, "Keep only");
+                    if settings.strip == StripMode::Keep {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut settings.keep_chunks)
+                                .hint_text("iCCP,sRGB")
+                                .desired_width(100.0),
+                        );
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Interlacing");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut settings.interlace, InterlaceMode::Keep, "Keep");
+                    ui.radio_value(&mut settings.interlace, InterlaceMode::Off, "Off");
+                    ui.radio_value(&mut settings.interlace, InterlaceMode::Adam7, "Adam7");
+                });
+                ui.end_row();
+
+                ui.label("Reductions");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut settings.bit_depth_reduction, "Bit depth");
+                    ui.checkbox(&mut settings.color_type_reduction, "Color type");
+                    ui.checkbox(&mut settings.palette_reduction, "Palette");
+                    ui.checkbox(&mut settings.grayscale_reduction, "Grayscale");
+                });
+                ui.end_row();
+
+                ui.label("Alpha");
+                ui.checkbox(
+                    &mut settings.optimize_alpha,
+                    "Clean up colors of fully transparent pixels",
+                );
+                ui.end_row();
+
+                ui.label("Verification");
+                ui.checkbox(verify, "Compare pixels after optimizing")
+                    .on_hover_text(
+                        "Files whose pixels differ are restored from the original. \
+                         Lossy quantized files are not checked.",
+                    );
+                ui.end_row();
+
+                ui.label("Filters");
+                ui.vertical(|ui| {
+                    let mut custom = settings.filters.is_some();
+                    if ui
+                        .checkbox(&mut custom, "Custom (otherwise from preset)")
+                        .changed()
+                    {
+                        // 从当前预设的过滤器组合开始编辑
+                        settings.filters = custom.then(|| {
+                            oxipng::Options::from_preset(self.opt_lvl)
+                                .filter
+                                .into_iter()
+                                .collect()
+                        });
+                    }
+                    if let Some(filters) = &mut settings.filters {
+                        ui.horizontal_wrapped(|ui| {
+                            for (filter, name) in settings::FILTERS {
+                                let mut selected = filters.contains(&filter);
+                                if ui.checkbox(&mut selected, name).changed() {
+                                    if selected {
+                                        filters.push(filter);
+                                    } else {
+                                        filters.retain(|f| *f != filter);
+                                    }
+                                }
+                            }
+                        });
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Deflater");
+                ui.horizontal(|ui| {
+                    let deflater = &mut settings.deflater;
+                    if ui.radio(*deflater == Deflater::Preset, "Preset").clicked() {
+                        *deflater = Deflater::Preset;
+                    }
+                    if ui
+                        .radio(matches!(deflater, Deflater::Libdeflate(_)), "libdeflate")
+                        .clicked()
+                    {
+                        *deflater = Deflater::Libdeflate(11);
+                    }
+                    if ui
+                        .radio(matches!(deflater, Deflater::Zopfli(_)), "Zopfli")
+                        .clicked()
+                    {
+                        *deflater = Deflater::Zopfli(15);
+                    }
+                    match deflater {
+                        Deflater::Preset => {}
+                        Deflater::Libdeflate(level) => {
+                            ui.add(egui::Slider::new(level, 0..=12).text("level"));
+                        }
+                        Deflater::Zopfli(iterations) => {
+                            ui.add(egui::Slider::new(iterations, 1..=255).text("iterations"));
+                        }
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Timeout");
+                ui.add(
+                    egui::DragValue::new(&mut settings.timeout_secs)
+                        .range(0..=3600)
+                        .suffix(" s (0 = none)"),
+                );
+                ui.end_row();
+            });
+
+            if ui.button("Reset advanced options").clicked() {
+                *settings = Settings::default();
+            }
+        });
+    }
+
+    // 返回被点击、需要打开对比的文件
+    fn results_ui(&self, ui: &mut egui::Ui) -> Option<FileResult> {
+        let results = match &self.batch {
+            Some(batch) => &batch.results,
+            None => &self.results,
+        };
+        let mut clicked = None;
+        egui::ScrollArea::both().show(ui, |ui| {
+            egui::Grid::new("results")
+                .num_columns(7)
+                .striped(true)
+                .show(ui, |ui| {
+                    for header in [
+                        "File",
+                        "Original",
+                        "Optimized",
+                        "Saved",
+                        "Time",
+                        "Quality",
+                        "Error",
+                    ] {
+                        ui.strong(header);
+                    }
+                    ui.end_row();
+
+                    for result in results {
+                        let name = result
+                            .path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string();
+                        let hover = if result.output == result.path {
+                            result.path.display().to_string()
+                        } else {
+                            format!("{}\n→ {}", result.path.display(), result.output.display())
+                        };
+                        if result.error.is_some() {
+                            ui.label(name).on_hover_text(hover);
+                        } else {
+                            let selected = self
+                                .comparison
+                                .as_ref()
+                                .is_some_and(|c| c.path == result.path);
+                            if ui
+                                .selectable_label(selected, name)
+                                .on_hover_text(format!("{}\nClick to compare", hover))
+                                .clicked()
+                            {
+                                clicked = Some(result.clone());
+                            }
+                        }
+                        ui.label(optimize::format_size(result.original));
+                        if let Some(error) = &result.error {
+                            ui.label("-");
+                            ui.label("-");
+                            ui.label(format!("{:.2}s", result.elapsed.as_secs_f64()));
+                            ui.label("-");
+                            ui.colored_label(egui::Color32::LIGHT_RED, error);
+                        } else {
+                            ui.label(optimize::format_size(result.optimized));
+                            ui.label(format!("{:.1}%", result.saved_percent()));
+                            ui.label(format!("{:.2}s", result.elapsed.as_secs_f64()));
+                            match &result.quality {
+                                Some(quality) if quality.applied => {
+                                    ui.label(format!("SSIM {:.4}", quality.ssim))
+                                        .on_hover_text(quality.describe());
+                                }
+                                Some(quality) => {
+                                    ui.weak("lossless").on_hover_text(format!(
+                                        "Quantization not applied: {}",
+                                        quality.describe()
+                                    ));
+                                }
+                                None if result.verified => {
+                                    ui.label("✔ pixel-exact");
+                                }
+                                None => {
+                                    ui.label("");
+                                }
+                            }
+                            ui.label("");
+                        }
+                        ui.end_row();
+                    }
+
+                    // 正在处理的文件
+                    if let Some(batch) = &self.batch {
+                        for path in batch.running.values() {
+                            ui.label(
+                                path.file_name()
+                                    .unwrap_or_default()
+                                    .to_string_lossy()
+                                    .to_string(),
+                            )
+                            .on_hover_text(path.display().to_string());
+                            ui.spinner();
+                            ui.weak("Optimizing...");
+                            ui.label("");
+                            ui.label("");
+                            ui.label("");
+                            ui.label("");
+                            ui.end_row();
+                        }
+                    }
+
+                    let totals = optimize::totals(results);
+                    ui.strong(format!("Total ({} files)", totals.files));
+                    ui.strong(optimize::format_size(totals.original));
+                    ui.strong(optimize::format_size(totals.optimized));
+                    ui.strong(format!("{:.1}%", totals.saved_percent()));
+                    ui.strong(format!("{:.2}s", totals.elapsed.as_secs_f64()));
+                    ui.label("");
+                    if totals.failed > 0 {
+                        ui.strong(format!("{} failed", totals.failed));
+                    } else {
+                        ui.label("");
+                    }
+                    ui.end_row();
+                });
+        });
+        clicked
     }
 }
 
 impl eframe::App for PngCompress {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_batch();
+        let running = self.batch.is_some();
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Oxipng Optimizer");
             ui.add_space(20.0);
 
             // 文件拖放处理
-            if !ctx.input(|i| i.raw.dropped_files.is_empty()) {
+            if !running && !ctx.input(|i| i.raw.dropped_files.is_empty()) {
                 let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
-                let png_images: Vec<PathBuf> = dropped_files
-                    .into_iter()
-                    .filter_map(|f| f.path)
-                    .filter(|f| {
-                        f.extension()
-                            .and_then(|ext| ext.to_str())
-                            .map_or(false, |ext| ext.eq_ignore_ascii_case("png"))
-                    })
-                    .collect();
-                self.status_message = format!("Select {} file(s)", png_images.len());
-                self.image_path = Some(png_images);
+                let paths: Vec<PathBuf> =
+                    dropped_files.into_iter().filter_map(|f| f.path).collect();
+                self.set_inputs(paths);
             }
 
-            // 选择文件
-            ui.horizontal(|ui| {
-                if ui.button("Select files").clicked() {
-                    if let Some(path) = FileDialog::new()
-                        .add_filter("PNG Images", &["png", "PNG"])
-                        .pick_files()
-                    {
-                        self.status_message = format!("Select {} file(s)", path.len());
-                        self.image_path = Some(path);
+            // 处理期间禁止修改输入与选项
+            ui.add_enabled_ui(!running, |ui| {
+                // 选择文件
+                ui.horizontal(|ui| {
+                    if ui.button("Select files").clicked() {
+                        let mut dialog =
+                            FileDialog::new().add_filter("PNG Images", &["png", "PNG"]);
+                        if self.convert {
+                            dialog = dialog.add_filter("Other Images", &convert::EXTENSIONS);
+                        }
+                        if let Some(path) = dialog.pick_files() {
+                            self.set_inputs(path);
+                        }
                     }
-                }
 
-                // 清除按钮
-                if ui.button("Clear").clicked() {
-                    self.clear_state();
-                }
-            });
+                    if ui.button("Select folder").clicked() {
+                        if let Some(path) = FileDialog::new().pick_folders() {
+                            self.set_inputs(path);
+                        }
+                    }
 
-            ui.add_space(10.0);
+                    // 清除按钮
+                    if ui.button("Clear").clicked() {
+                        self.clear_state();
+                    }
+                });
 
-            ui.label(format!("Current: Preset {}", self.opt_lvl));
-            ui.add(egui::Slider::new(&mut self.opt_lvl, 0..=6).text("Preset level"));
+                // 目录展开选项，排除规则在输入框失去焦点时生效
+                ui.horizontal(|ui| {
+                    let mut changed = ui.checkbox(&mut self.recursive, "Recursive").changed();
+                    ui.label("Exclude");
+                    changed |= ui
+                        .add(
+                            egui::TextEdit::singleline(&mut self.excludes)
+                                .hint_text("*.min.png, thumbs/*")
+                                .desired_width(200.0),
+                        )
+                        .lost_focus();
+                    if changed && !self.inputs.is_empty() {
+                        self.expand_inputs();
+                    }
+                });
+
+                // 其他格式转换选项
+                ui.horizontal(|ui| {
+                    if ui
+                        .checkbox(&mut self.convert, "Convert BMP/TIFF/WebP/GIF/JPEG")
+                        .changed()
+                        && !self.inputs.is_empty()
+                    {
+                        self.expand_inputs();
+                    }
+                    ui.add_enabled_ui(self.convert, |ui| {
+                        for originals in Originals::ALL {
+                            ui.radio_value(&mut self.originals, originals, originals.label());
+                        }
+                    });
+                });
+
+                ui.add_space(10.0);
+
+                ui.label(format!("Current: Preset {}", self.opt_lvl));
+                ui.add(egui::Slider::new(&mut self.opt_lvl, 0..=6).text("Preset level"));
+                self.advanced_ui(ui);
+                self.quantize_ui(ui);
+                self.output_ui(ui);
+            });
 
             ui.add_space(10.0);
 
             ui.separator();
 
             ui.add_space(10.0);
-            ui.label("Drag and Drop files here");
+            ui.label(if self.convert {
+                "Drag and Drop images or folders here"
+            } else {
+                "Drag and Drop files or folders here"
+            });
             ui.add_space(20.0);
             ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
                 // 执行按钮
-                let can_execute = self.image_path.is_some();
+                let can_execute = self.image_path.is_some() && !running;
 
                 if ui
                     .add_enabled(can_execute, egui::Button::new("Process"))
                     .clicked()
                 {
-                    self.execute_oxipng();
+                    self.execute_oxipng(ctx);
+                }
+
+                // 进度与取消
+                if let Some(batch) = &self.batch {
+                    self.progress_ui(ui, batch);
                 }
 
                 // 状态信息显示
                 if !self.status_message.is_empty() {
                     ui.label(&self.status_message);
                 }
+
+                // 结果表格
+                if !self.results.is_empty() || running {
+                    ui.with_layout(egui::Layout::top_down(egui::Align::Min), |ui| {
+                        if let Some(result) = self.results_ui(ui) {
+                            self.open_comparison(ui.ctx(), &result);
+                        }
+                    });
+                }
             });
         });
+
+        self.comparison_ui(ctx);
     }
 }
 
 fn main() -> eframe::Result<()> {
+    // 带参数启动时进入命令行模式
+    let args: Vec<std::ffi::OsString> = std::env::args_os().skip(1).collect();
+    if !args.is_empty() {
+        cli::attach_console();
+        std::process::exit(cli::run(&args));
+    }
+
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
-            .with_inner_size([480.0, 320.0])
+            .with_inner_size([640.0, 480.0])
             .with_title("Oxipng Optimizer"),
         ..Default::default()
     };
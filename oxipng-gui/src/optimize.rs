@@ -0,0 +1,417 @@
+use crate::convert::{self, Originals};
+use crate::input;
+use crate::output::Target;
+use crate::quantize::{self, Quality, Quantize};
+use crate::verify;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// 一批文件共用的处理设置
+#[derive(Clone, Debug)]
+pub struct Job {
+    pub options: oxipng::Options,
+    pub quantize: Option<Quantize>,
+    pub preserve_attrs: bool,
+    // 无损优化后逐像素比较，不一致时恢复原文件
+    pub verify: bool,
+    // 其他格式转换后如何处理原文件
+    pub originals: Originals,
+}
+
+// 单个文件的优化结果
+#[derive(Clone, Debug)]
+pub struct FileResult {
+    pub path: PathBuf,
+    // 实际写入的文件，覆盖模式下与 path 相同
+    pub output: PathBuf,
+    // 覆盖前保存的原文件副本
+    pub backup: Option<PathBuf>,
+    pub original: u64,
+    pub optimized: u64,
+    pub elapsed: Duration,
+    pub error: Option<String>,
+    // 启用量化时的质量指标
+    pub quality: Option<Quality>,
+    // 已通过逐像素校验
+    pub verified: bool,
+}
+
+impl FileResult {
+    // 尚未处理的结果，大小与耗时在处理过程中填写
+    pub fn new(path: impl Into<PathBuf>, output: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            output: output.into(),
+            backup: None,
+            original: 0,
+            optimized: 0,
+            elapsed: Duration::ZERO,
+            error: None,
+            quality: None,
+            verified: false,
+        }
+    }
+
+    // 处理后仍可读取原图的位置，直接覆盖且没有备份时为 None
+    pub fn original_source(&self) -> Option<&Path> {
+        if self.output != self.path {
+            Some(&self.path)
+        } else {
+            self.backup.as_deref()
+        }
+    }
+
+    pub fn saved(&self) -> u64 {
+        self.original.saturating_sub(self.optimized)
+    }
+
+    pub fn saved_percent(&self) -> f64 {
+        percent(self.saved(), self.original)
+    }
+}
+
+// 汇总行
+#[derive(Clone, Debug, Default)]
+pub struct Totals {
+    pub files: usize,
+    pub failed: usize,
+    pub original: u64,
+    pub optimized: u64,
+    pub elapsed: Duration,
+}
+
+impl Totals {
+    pub fn saved(&self) -> u64 {
+        self.original.saturating_sub(self.optimized)
+    }
+
+    pub fn saved_percent(&self) -> f64 {
+        percent(self.saved(), self.original)
+    }
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64 * 100.0
+    }
+}
+
+// 失败的文件不计入大小统计
+pub fn totals(results: &[FileResult]) -> Totals {
+    let mut totals = Totals {
+        files: results.len(),
+        ..Default::default()
+    };
+    for result in results {
+        totals.elapsed += result.elapsed;
+        if result.error.is_some() {
+            totals.failed += 1;
+            continue;
+        }
+        totals.original += result.original;
+        totals.optimized += result.optimized;
+    }
+    totals
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// 按 target 优化文件；覆盖原文件时 oxipng 在无法缩小时不会改写，
+// 写入其他位置时则原样复制。启用量化且结果更小时写入量化后的文件，
+// 其他格式的文件转换为 PNG 后写入
+pub fn optimize_file(path: &Path, target: &Target, job: &Job) -> FileResult {
+    let start = Instant::now();
+    let mut result = FileResult::new(path, &target.output);
+    result.backup = target.backup.clone();
+
+    let meta = match std::fs::metadata(path) {
+        Ok(meta) => meta,
+        Err(e) => {
+            result.error = Some(e.to_string());
+            return result;
+        }
+    };
+    result.original = meta.len();
+
+    // 其他格式先转换为未优化的 PNG，转换结果不得覆盖已有文件
+    let converted = if input::is_png(path) {
+        None
+    } else {
+        if target.output.exists() {
+            result.error = Some(format!("{} already exists", target.output.display()));
+            return result;
+        }
+        match convert::to_png(path) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                result.elapsed = start.elapsed();
+                result.error = Some(e);
+                return result;
+            }
+        }
+    };
+
+    // 校验需要优化前的数据，覆盖前读入内存
+    let reference = match (&converted, job.verify) {
+        (Some(data), true) => Some(data.clone()),
+        (None, true) => match std::fs::read(path) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                result.error = Some(e.to_string());
+                return result;
+            }
+        },
+        (_, false) => None,
+    };
+
+    if let Err(e) = prepare(path, target) {
+        result.error = Some(e);
+        return result;
+    }
+
+    // 转换得到的 PNG 先做无损优化，作为量化结果的比较基准
+    let lossless = match converted
+        .as_deref()
+        .map(|png| oxipng::optimize_from_memory(png, &job.options))
+    {
+        Some(Err(e)) => {
+            result.elapsed = start.elapsed();
+            result.error = Some(e.to_string());
+            return result;
+        }
+        lossless => lossless.and_then(Result::ok),
+    };
+    let baseline = lossless
+        .as_ref()
+        .map_or(result.original, |data| data.len() as u64);
+
+    let mut quantized = None;
+    if let Some(settings) = &job.quantize {
+        match quantize::quantize_file(path, settings, &job.options) {
+            Ok(q) => {
+                quantized = q.data.filter(|data| (data.len() as u64) < baseline);
+                result.quality = Some(Quality {
+                    applied: quantized.is_some(),
+                    ..q.quality
+                });
+            }
+            Err(e) => {
+                result.elapsed = start.elapsed();
+                result.error = Some(e);
+                return result;
+            }
+        }
+    }
+
+    let outcome = match (&quantized, &lossless) {
+        (Some(data), _) | (None, Some(data)) => {
+            write_output(&meta, &target.output, data, job.preserve_attrs)
+        }
+        (None, None) => oxipng::optimize(
+            &oxipng::InFile::Path(path.to_path_buf()),
+            &oxipng::OutFile::Path {
+                path: (target.output != path).then(|| target.output.clone()),
+                preserve_attrs: job.preserve_attrs,
+            },
+            &job.options,
+        )
+        .map_err(|e| e.to_string()),
+    };
+
+    match outcome.and_then(|()| {
+        std::fs::metadata(&target.output)
+            .map(|meta| meta.len())
+            .map_err(|e| e.to_string())
+    }) {
+        Ok(size) => result.optimized = size,
+        Err(e) => {
+            result.optimized = result.original;
+            result.error = Some(e);
+        }
+    }
+
+    // 有损量化的输出不做校验
+    if let (Some(reference), None, None) = (&reference, &quantized, &result.error) {
+        let checked = std::fs::read(&target.output)
+            .map_err(|e| format!("Unable to read output: {}", e))
+            .and_then(|data| verify::compare(reference, &data, job.options.optimize_alpha));
+        match checked {
+            Ok(()) => result.verified = true,
+            Err(difference) => {
+                let restored = if converted.is_some() {
+                    "unoptimized PNG written"
+                } else {
+                    "original restored"
+                };
+                result.error = Some(
+                    match write_output(&meta, &target.output, reference, job.preserve_attrs) {
+                        Ok(()) => {
+                            result.optimized = reference.len() as u64;
+                            format!("{}; {}", difference, restored)
+                        }
+                        Err(e) => format!("{}; restoring the original failed: {}", difference, e),
+                    },
+                );
+            }
+        }
+    }
+
+    // 转换成功后按设置处理原文件
+    if converted.is_some() && result.error.is_none() {
+        if let Err(e) = convert::dispose(path, job.originals) {
+            result.error = Some(format!(
+                "Converted, but handling the original failed: {}",
+                e
+            ));
+        }
+    }
+    result.elapsed = start.elapsed();
+    result
+}
+
+// 写入数据，按需保留原文件的权限与时间戳
+fn write_output(
+    meta: &Metadata,
+    output: &Path,
+    data: &[u8],
+    preserve_attrs: bool,
+) -> Result<(), String> {
+    std::fs::write(output, data)
+        .map_err(|e| format!("Unable to write to {}: {}", output.display(), e))?;
+    if preserve_attrs {
+        std::fs::set_permissions(output, meta.permissions()).map_err(|e| e.to_string())?;
+        filetime::set_file_times(
+            output,
+            filetime::FileTime::from_last_access_time(meta),
+            filetime::FileTime::from_last_modification_time(meta),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// 创建输出目录并备份原文件，已有的备份保留不动，以免重复处理时被覆盖
+fn prepare(path: &Path, target: &Target) -> Result<(), String> {
+    if let Some(backup) = &target.backup {
+        if let Some(dir) = backup.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Backup failed: {}", e))?;
+        }
+        if !backup.exists() {
+            std::fs::copy(path, backup).map_err(|e| format!("Backup failed: {}", e))?;
+        }
+    }
+    if let Some(dir) = target.output.parent() {
+        if !dir.as_os_str().is_empty() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(original: u64, optimized: u64, error: Option<&str>) -> FileResult {
+        FileResult {
+            original,
+            optimized,
+            elapsed: Duration::from_millis(250),
+            error: error.map(str::to_string),
+            ..FileResult::new("a.png", "a.png")
+        }
+    }
+
+    #[test]
+    fn totals_skip_failed_sizes() {
+        let results = [
+            result(1000, 600, None),
+            result(500, 500, None),
+            result(800, 0, Some("timed out")),
+        ];
+
+        let totals = totals(&results);
+        assert_eq!((totals.files, totals.failed), (3, 1));
+        assert_eq!((totals.original, totals.optimized), (1500, 1100));
+        assert_eq!(totals.saved(), 400);
+        assert!((totals.saved_percent() - 26.666).abs() < 0.01);
+        assert_eq!(totals.elapsed, Duration::from_millis(750));
+    }
+
+    #[test]
+    fn empty_and_grown_results_save_nothing() {
+        let totals = totals(&[]);
+        assert_eq!(totals.files, 0);
+        assert_eq!(totals.saved_percent(), 0.0);
+
+        // 写入其他位置时结果可能比原文件大
+        let grown = result(100, 120, None);
+        assert_eq!(grown.saved(), 0);
+        assert_eq!(grown.saved_percent(), 0.0);
+    }
+
+    #[test]
+    fn conversion_never_overwrites_existing_png() {
+        let dir = std::env::temp_dir().join(format!("oxipng-gui-exists-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let bmp = dir.join("x.bmp");
+        let png = dir.join("x.png");
+        let mut bmp_data = Vec::new();
+        image::RgbaImage::new(2, 2)
+            .write_to(
+                &mut std::io::Cursor::new(&mut bmp_data),
+                image::ImageFormat::Bmp,
+            )
+            .unwrap();
+        std::fs::write(&bmp, bmp_data).unwrap();
+        std::fs::write(&png, b"keep").unwrap();
+
+        let target = Target {
+            output: png.clone(),
+            backup: None,
+        };
+        let job = Job {
+            options: oxipng::Options::default(),
+            quantize: None,
+            preserve_attrs: false,
+            verify: false,
+            originals: Originals::Backup,
+        };
+        let result = optimize_file(&bmp, &target, &job);
+        let kept = (std::fs::read(&png).unwrap(), bmp.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(
+            result.error,
+            Some(format!("{} already exists", png.display()))
+        );
+        assert_eq!(kept, (b"keep".to_vec(), true));
+    }
+
+    #[test]
+    fn formats_sizes_with_binary_units() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+        assert_eq!(format_size(3 << 30), "3.0 GiB");
+        assert_eq!(format_size(2048 << 30), "2048.0 GiB");
+    }
+}
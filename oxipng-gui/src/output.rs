@@ -0,0 +1,252 @@
+use crate::input;
+use std::path::{Path, PathBuf};
+
+// 优化结果的写入方式
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputMode {
+    // 覆盖原文件
+    #[default]
+    Overwrite,
+    // 写入输出目录，保持子目录结构
+    Directory,
+    // 在原文件旁写入带后缀的新文件
+    Suffix,
+    // 覆盖前把原文件备份到同级的 .bak 目录
+    Backup,
+}
+
+pub const BACKUP_DIR: &str = ".bak";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutputSettings {
+    pub mode: OutputMode,
+    pub directory: Option<PathBuf>,
+    // 替换 ".png" 扩展名，如 "a.png" -> "a.min.png"
+    pub suffix: String,
+    // 保留原文件的权限与时间戳
+    pub preserve_attrs: bool,
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        Self {
+            mode: OutputMode::Overwrite,
+            directory: None,
+            suffix: ".min.png".to_string(),
+            preserve_attrs: true,
+        }
+    }
+}
+
+// 单个文件的写入计划
+#[derive(Clone, Debug, PartialEq)]
+pub struct Target {
+    pub output: PathBuf,
+    pub backup: Option<PathBuf>,
+}
+
+impl OutputSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.mode {
+            OutputMode::Directory if self.directory.is_none() => {
+                Err("Choose an output folder".to_string())
+            }
+            OutputMode::Suffix
+                if self.suffix.trim().is_empty()
+                    || self.suffix.trim().eq_ignore_ascii_case(".png")
+                    || self.suffix.contains(['/', '\\']) =>
+            {
+                Err(format!("Invalid suffix: {:?}", self.suffix))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // 写入输出目录时的目录
+    pub fn output_dir(&self) -> Option<&Path> {
+        match self.mode {
+            OutputMode::Directory => self.directory.as_deref(),
+            _ => None,
+        }
+    }
+
+    // root 为输入文件的公共目录，输出目录中按相对 root 的路径存放。
+    // 其他格式转换后扩展名改为 .png，原文件不会被覆盖，因此不需要备份
+    pub fn target(&self, path: &Path, root: &Path) -> Target {
+        let mut target = self.png_target(path, root);
+        if !input::is_png(path) {
+            target.output.set_extension("png");
+            target.backup = None;
+        }
+        target
+    }
+
+    fn png_target(&self, path: &Path, root: &Path) -> Target {
+        let mut target = Target {
+            output: path.to_path_buf(),
+            backup: None,
+        };
+        match self.mode {
+            OutputMode::Overwrite => {}
+            OutputMode::Directory => {
+                let Some(directory) = &self.directory else {
+                    return target;
+                };
+                let relative = path
+                    .strip_prefix(root)
+                    .ok()
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .unwrap_or_else(|| Path::new(path.file_name().unwrap_or_default()));
+                target.output = directory.join(relative);
+            }
+            OutputMode::Suffix => {
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                target.output = path.with_file_name(format!("{}{}", stem, self.suffix.trim()));
+            }
+            OutputMode::Backup => {
+                let name = path.file_name().unwrap_or_default();
+                let parent = path.parent().unwrap_or(Path::new(""));
+                target.backup = Some(parent.join(BACKUP_DIR).join(name));
+            }
+        }
+        target
+    }
+}
+
+// 若干目录的最长公共前缀
+pub fn common_root<'a>(dirs: impl IntoIterator<Item = &'a Path>) -> PathBuf {
+    let mut dirs = dirs.into_iter();
+    let Some(first) = dirs.next() else {
+        return PathBuf::new();
+    };
+    let mut root = first.to_path_buf();
+    for dir in dirs {
+        while !dir.starts_with(&root) {
+            if !root.pop() {
+                return PathBuf::new();
+            }
+        }
+    }
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(mode: OutputMode) -> OutputSettings {
+        OutputSettings {
+            mode,
+            directory: Some(PathBuf::from("/out")),
+            ..Default::default()
+        }
+    }
+
+    fn target(output: &str, backup: Option<&str>) -> Target {
+        Target {
+            output: PathBuf::from(output),
+            backup: backup.map(PathBuf::from),
+        }
+    }
+
+    #[test]
+    fn targets_for_each_mode() {
+        let path = Path::new("/in/sub/a.png");
+        let root = Path::new("/in");
+        assert_eq!(
+            settings(OutputMode::Overwrite).target(path, root),
+            target("/in/sub/a.png", None)
+        );
+        assert_eq!(
+            settings(OutputMode::Directory).target(path, root),
+            target("/out/sub/a.png", None)
+        );
+        assert_eq!(
+            settings(OutputMode::Suffix).target(path, root),
+            target("/in/sub/a.min.png", None)
+        );
+        assert_eq!(
+            settings(OutputMode::Backup).target(path, root),
+            target("/in/sub/a.png", Some("/in/sub/.bak/a.png"))
+        );
+    }
+
+    #[test]
+    fn directory_target_outside_root_uses_file_name() {
+        let output = settings(OutputMode::Directory);
+        assert_eq!(
+            output.target(Path::new("/other/a.png"), Path::new("/in")),
+            target("/out/a.png", None)
+        );
+        // 单个文件作为输入时 root 就是文件本身
+        assert_eq!(
+            output.target(Path::new("/in/a.png"), Path::new("/in/a.png")),
+            target("/out/a.png", None)
+        );
+    }
+
+    #[test]
+    fn converted_files_get_png_extension_without_backup() {
+        let root = Path::new("/in");
+        assert_eq!(
+            settings(OutputMode::Backup).target(Path::new("/in/a.jpg"), root),
+            target("/in/a.png", None)
+        );
+        assert_eq!(
+            settings(OutputMode::Directory).target(Path::new("/in/b.webp"), root),
+            target("/out/b.png", None)
+        );
+        assert_eq!(
+            settings(OutputMode::Suffix).target(Path::new("/in/c.bmp"), root),
+            target("/in/c.min.png", None)
+        );
+    }
+
+    #[test]
+    fn common_root_of_directories() {
+        assert_eq!(common_root([]), PathBuf::new());
+        assert_eq!(common_root([Path::new("/a/b/c")]), PathBuf::from("/a/b/c"));
+        assert_eq!(
+            common_root([Path::new("/a/b/c"), Path::new("/a/b/d"), Path::new("/a/b")]),
+            PathBuf::from("/a/b")
+        );
+        // 按路径组件比较，而不是字符串前缀
+        assert_eq!(
+            common_root([Path::new("/a/bc"), Path::new("/a/b")]),
+            PathBuf::from("/a")
+        );
+        assert_eq!(
+            common_root([Path::new("a/b"), Path::new("c/d")]),
+            PathBuf::new()
+        );
+    }
+
+    #[test]
+    fn validates_settings() {
+        assert!(OutputSettings::default().validate().is_ok());
+        assert!(settings(OutputMode::Directory).validate().is_ok());
+        assert_eq!(
+            OutputSettings {
+                mode: OutputMode::Directory,
+                ..Default::default()
+            }
+            .validate(),
+            Err("Choose an output folder".to_string())
+        );
+
+        for suffix in ["", "  ", ".png", " .PNG ", "/x.png", "\\x.png"] {
+            let output = OutputSettings {
+                mode: OutputMode::Suffix,
+                suffix: suffix.to_string(),
+                ..Default::default()
+            };
+            assert!(output.validate().is_err(), "{:?}", suffix);
+        }
+        let output = OutputSettings {
+            mode: OutputMode::Suffix,
+            suffix: "_small.png".to_string(),
+            ..Default::default()
+        };
+        assert!(output.validate().is_ok());
+    }
+}
@@ -0,0 +1,586 @@
+use image::ImageDecoder;
+use oxipng::{BitDepth, ColorType, RawImage, RGBA8};
+use std::collections::HashMap;
+use std::path::Path;
+
+// 有损调色板量化，在 oxipng 无损优化之前执行
+#[derive(Clone, Debug, PartialEq)]
+pub struct Quantize {
+    // 质量分数为 SSIM × 100；低于下限时放弃量化，达到上限时尝试更少的颜色
+    pub quality_min: u8,
+    pub quality_max: u8,
+    // 误差扩散强度，0 为不抖动，1 为完整的 Floyd-Steinberg
+    pub dithering: f32,
+    pub max_colors: u16,
+}
+
+impl Default for Quantize {
+    fn default() -> Self {
+        Self {
+            quality_min: 70,
+            quality_max: 95,
+            dithering: 1.0,
+            max_colors: 256,
+        }
+    }
+}
+
+impl Quantize {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.quality_min > self.quality_max || self.quality_max > 100 {
+            return Err(format!(
+                "Invalid quality range: {}-{}",
+                self.quality_min, self.quality_max
+            ));
+        }
+        if !(2..=256).contains(&self.max_colors) {
+            return Err(format!("Max colors must be 2-256, got {}", self.max_colors));
+        }
+        if !(0.0..=1.0).contains(&self.dithering) {
+            return Err(format!("Dithering must be 0-1, got {}", self.dithering));
+        }
+        Ok(())
+    }
+}
+
+// 量化后与原图的差异
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quality {
+    pub colors: usize,
+    // 无差异时为无穷大
+    pub psnr: f64,
+    pub ssim: f64,
+    // 未达到最低质量时为 false，文件按无损方式处理
+    pub applied: bool,
+}
+
+impl Quality {
+    pub fn score(&self) -> u8 {
+        (self.ssim * 100.0).round().clamp(0.0, 100.0) as u8
+    }
+
+    pub fn describe(&self) -> String {
+        let psnr = if self.psnr.is_finite() {
+            format!("{:.1} dB", self.psnr)
+        } else {
+            "lossless".to_string()
+        };
+        format!(
+            "{} colors, SSIM {:.4}, PSNR {}",
+            self.colors, self.ssim, psnr
+        )
+    }
+}
+
+pub struct Quantized {
+    pub quality: Quality,
+    // 优化后的 PNG 数据，未应用量化时为 None
+    pub data: Option<Vec<u8>>,
+}
+
+// 完全透明的像素统一为 0，避免无意义的颜色占用调色板
+fn normalize(pixel: [u8; 4]) -> [u8; 4] {
+    if pixel[3] == 0 {
+        [0; 4]
+    } else {
+        pixel
+    }
+}
+
+// 解码后的 RGBA 像素
+pub struct Decoded {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<[u8; 4]>,
+    pub icc: Option<Vec<u8>>,
+}
+
+// 按内容识别格式，其他格式的文件同样可以解码
+pub fn decode(path: &Path) -> Result<Decoded, String> {
+    let mut decoder = image::ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| e.to_string())?
+        .into_decoder()
+        .map_err(|e| e.to_string())?;
+    let icc = decoder.icc_profile().ok().flatten();
+    let image = image::DynamicImage::from_decoder(decoder)
+        .map_err(|e| e.to_string())?
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+    let pixels = image.pixels().map(|p| normalize(p.0)).collect();
+    Ok(Decoded {
+        width,
+        height,
+        pixels,
+        icc,
+    })
+}
+
+fn histogram(pixels: &[[u8; 4]]) -> Vec<([u8; 4], u32)> {
+    let mut counts: HashMap<[u8; 4], u32> = HashMap::new();
+    for &pixel in pixels {
+        *counts.entry(pixel).or_default() += 1;
+    }
+    counts.into_iter().collect()
+}
+
+// 方差最大的通道及该通道的加权平方误差和
+fn widest(colors: &[([u8; 4], u32)]) -> (usize, f64) {
+    let total: f64 = colors.iter().map(|(_, n)| *n as f64).sum();
+    (0..4)
+        .map(|channel| {
+            let mean = colors
+                .iter()
+                .map(|(c, n)| c[channel] as f64 * *n as f64)
+                .sum::<f64>()
+                / total;
+            let error: f64 = colors
+                .iter()
+                .map(|(c, n)| (c[channel] as f64 - mean).powi(2) * *n as f64)
+                .sum();
+            (channel, error)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap_or((0, 0.0))
+}
+
+fn average(colors: &[([u8; 4], u32)]) -> RGBA8 {
+    let mut sum = [0u64; 4];
+    let mut total = 0u64;
+    for (color, count) in colors {
+        for channel in 0..4 {
+            sum[channel] += color[channel] as u64 * *count as u64;
+        }
+        total += *count as u64;
+    }
+    let total = total.max(1);
+    let [r, g, b, a] = sum.map(|s| ((s + total / 2) / total) as u8);
+    RGBA8::new(r, g, b, a)
+}
+
+// 中位切分：反复按加权中位数切开平方误差最大的盒子
+fn median_cut(histogram: &[([u8; 4], u32)], colors: usize) -> Vec<RGBA8> {
+    let mut boxes = vec![histogram.to_vec()];
+    while boxes.len() < colors {
+        let Some((i, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, error) = widest(b);
+                (i, channel, error)
+            })
+            .filter(|(_, _, error)| *error > 0.0)
+            .max_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(i);
+        colors.sort_unstable_by_key(|(c, _)| c[channel]);
+        let total: u64 = colors.iter().map(|(_, n)| *n as u64).sum();
+        let mut seen = 0;
+        let split = colors
+            .iter()
+            .position(|(_, n)| {
+                seen += *n as u64;
+                seen * 2 >= total
+            })
+            .map_or(1, |p| p + 1)
+            .clamp(1, colors.len() - 1);
+        let rest = colors.split_off(split);
+        boxes.push(colors);
+        boxes.push(rest);
+    }
+    boxes.iter().map(|b| average(b)).collect()
+}
+
+// 以各颜色最近的调色板项重新求加权平均，迭代几次以减小误差
+fn refine(histogram: &[([u8; 4], u32)], palette: &mut [RGBA8], iterations: usize) {
+    for _ in 0..iterations {
+        let mut sums = vec![([0u64; 4], 0u64); palette.len()];
+        for (color, count) in histogram {
+            let (sum, total) = &mut sums[nearest(palette, *color) as usize];
+            for channel in 0..4 {
+                sum[channel] += color[channel] as u64 * *count as u64;
+            }
+            *total += *count as u64;
+        }
+        for (entry, (sum, total)) in palette.iter_mut().zip(sums) {
+            if total > 0 {
+                let [r, g, b, a] = sum.map(|s| ((s + total / 2) / total) as u8);
+                *entry = RGBA8::new(r, g, b, a);
+            }
+        }
+    }
+}
+
+fn nearest(palette: &[RGBA8], color: [u8; 4]) -> u8 {
+    let distance = |p: &RGBA8| {
+        [p.r, p.g, p.b, p.a]
+            .iter()
+            .zip(color)
+            .map(|(&a, b)| (a as i32 - b as i32).pow(2))
+            .sum::<i32>()
+    };
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, p)| distance(p))
+        .map_or(0, |(i, _)| i as u8)
+}
+
+// 映射到调色板，dithering > 0 时按比例扩散误差
+fn remap(pixels: &[[u8; 4]], width: usize, palette: &[RGBA8], dithering: f32) -> Vec<u8> {
+    // 抖动后的颜色很分散，缓存过大时清空
+    let mut cache: HashMap<[u8; 4], u8> = HashMap::new();
+    let mut lookup = |color: [u8; 4]| {
+        if cache.len() > 1 << 16 {
+            cache.clear();
+        }
+        *cache
+            .entry(color)
+            .or_insert_with(|| nearest(palette, color))
+    };
+    if dithering <= 0.0 {
+        return pixels.iter().map(|&p| lookup(p)).collect();
+    }
+
+    let mut indices = Vec::with_capacity(pixels.len());
+    // 当前行与下一行的累计误差，两端各留一格
+    let mut current = vec![[0f32; 4]; width + 2];
+    let mut below = vec![[0f32; 4]; width + 2];
+    for row in pixels.chunks(width) {
+        for (x, pixel) in row.iter().enumerate() {
+            let mut exact = [0f32; 4];
+            let mut wanted = [0u8; 4];
+            for channel in 0..4 {
+                exact[channel] = pixel[channel] as f32 + current[x + 1][channel];
+                wanted[channel] = exact[channel].round().clamp(0.0, 255.0) as u8;
+            }
+            let index = lookup(normalize(wanted));
+            indices.push(index);
+
+            let chosen = palette[index as usize];
+            let chosen = [chosen.r, chosen.g, chosen.b, chosen.a];
+            for channel in 0..4 {
+                let error = (exact[channel] - chosen[channel] as f32) * dithering;
+                current[x + 2][channel] += error * 7.0 / 16.0;
+                below[x][channel] += error * 3.0 / 16.0;
+                below[x + 1][channel] += error * 5.0 / 16.0;
+                below[x + 2][channel] += error / 16.0;
+            }
+        }
+        std::mem::swap(&mut current, &mut below);
+        below.fill([0.0; 4]);
+    }
+    indices
+}
+
+// 四个通道的峰值信噪比，完全相同时为无穷大
+pub fn psnr(a: &[[u8; 4]], b: &[[u8; 4]]) -> f64 {
+    let sum: f64 = a
+        .iter()
+        .zip(b)
+        .flat_map(|(x, y)| {
+            x.iter()
+                .zip(y)
+                .map(|(&p, &q)| (p as f64 - q as f64).powi(2))
+        })
+        .sum();
+    let mse = sum / (a.len().max(1) * 4) as f64;
+    if mse == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (255.0 * 255.0 / mse).log10()
+    }
+}
+
+// 预乘透明度后的亮度
+fn luma(p: &[u8; 4]) -> f64 {
+    (0.299 * p[0] as f64 + 0.587 * p[1] as f64 + 0.114 * p[2] as f64) * p[3] as f64 / 255.0
+}
+
+// 亮度在 8×8 不重叠窗口上的平均 SSIM
+pub fn ssim(a: &[[u8; 4]], b: &[[u8; 4]], width: usize) -> f64 {
+    const WINDOW: usize = 8;
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let height = a.len() / width.max(1);
+    let mut total = 0.0;
+    let mut windows = 0;
+    for top in (0..height).step_by(WINDOW) {
+        for left in (0..width).step_by(WINDOW) {
+            let mut values = Vec::with_capacity(WINDOW * WINDOW);
+            for y in top..(top + WINDOW).min(height) {
+                for x in left..(left + WINDOW).min(width) {
+                    let i = y * width + x;
+                    values.push((luma(&a[i]), luma(&b[i])));
+                }
+            }
+            let n = values.len() as f64;
+            let mean_a = values.iter().map(|v| v.0).sum::<f64>() / n;
+            let mean_b = values.iter().map(|v| v.1).sum::<f64>() / n;
+            let (mut var_a, mut var_b, mut cov) = (0.0, 0.0, 0.0);
+            for (x, y) in &values {
+                var_a += (x - mean_a).powi(2);
+                var_b += (y - mean_b).powi(2);
+                cov += (x - mean_a) * (y - mean_b);
+            }
+            let (var_a, var_b, cov) = (var_a / n, var_b / n, cov / n);
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * cov + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+    if windows == 0 {
+        1.0
+    } else {
+        total / windows as f64
+    }
+}
+
+struct Attempt {
+    palette: Vec<RGBA8>,
+    indices: Vec<u8>,
+    quality: Quality,
+}
+
+fn attempt(
+    pixels: &[[u8; 4]],
+    width: usize,
+    histogram: &[([u8; 4], u32)],
+    colors: usize,
+    dithering: f32,
+) -> Attempt {
+    let mut palette = median_cut(histogram, colors);
+    if histogram.len() > colors {
+        refine(histogram, &mut palette, 3);
+    }
+    // 颜色数不超过调色板时无需抖动
+    let dithering = if histogram.len() <= colors {
+        0.0
+    } else {
+        dithering
+    };
+    let indices = remap(pixels, width, &palette, dithering);
+    let mapped: Vec<[u8; 4]> = indices
+        .iter()
+        .map(|&i| {
+            let p = palette[i as usize];
+            [p.r, p.g, p.b, p.a]
+        })
+        .collect();
+    let quality = Quality {
+        colors: palette.len(),
+        psnr: psnr(pixels, &mapped),
+        ssim: ssim(pixels, &mapped, width),
+        applied: true,
+    };
+    Attempt {
+        palette,
+        indices,
+        quality,
+    }
+}
+
+// 先以最大颜色数量化，达到质量上限时逐次减半颜色数，取仍满足上限的最少颜色
+pub fn quantize_file(
+    path: &Path,
+    settings: &Quantize,
+    options: &oxipng::Options,
+) -> Result<Quantized, String> {
+    let Decoded {
+        width,
+        height,
+        pixels,
+        icc,
+    } = decode(path)?;
+    let histogram = histogram(&pixels);
+    let size = width as usize;
+
+    let mut colors = settings.max_colors as usize;
+    let mut best = attempt(&pixels, size, &histogram, colors, settings.dithering);
+    if best.quality.score() < settings.quality_min {
+        return Ok(Quantized {
+            quality: Quality {
+                applied: false,
+                ..best.quality
+            },
+            data: None,
+        });
+    }
+    while best.quality.score() >= settings.quality_max && colors / 2 >= 2 {
+        colors /= 2;
+        let next = attempt(&pixels, size, &histogram, colors, settings.dithering);
+        if next.quality.score() < settings.quality_max {
+            break;
+        }
+        best = next;
+    }
+
+    let mut raw = RawImage::new(
+        width,
+        height,
+        ColorType::Indexed {
+            palette: best.palette,
+        },
+        BitDepth::Eight,
+        best.indices,
+    )
+    .map_err(|e| e.to_string())?;
+    if let Some(icc) = icc {
+        raw.add_icc_profile(&icc);
+    }
+    let data = raw
+        .create_optimized_png(options)
+        .map_err(|e| e.to_string())?;
+    Ok(Quantized {
+        quality: best.quality,
+        data: Some(data),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 每个像素颜色都不同的渐变
+    fn gradient(width: usize, height: usize) -> Vec<[u8; 4]> {
+        (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                [(x * 8) as u8, (y * 8) as u8, ((x + y) * 4) as u8, 255]
+            })
+            .collect()
+    }
+
+    fn colors(palette: &[RGBA8], indices: &[u8]) -> Vec<[u8; 4]> {
+        indices
+            .iter()
+            .map(|&i| {
+                let p = palette[i as usize];
+                [p.r, p.g, p.b, p.a]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_images_are_lossless() {
+        let pixels = gradient(20, 12);
+        assert_eq!(ssim(&pixels, &pixels, 20), 1.0);
+        assert_eq!(psnr(&pixels, &pixels), f64::INFINITY);
+
+        let quality = Quality {
+            colors: 2,
+            psnr: f64::INFINITY,
+            ssim: 1.0,
+            applied: true,
+        };
+        assert_eq!(quality.score(), 100);
+        assert_eq!(quality.describe(), "2 colors, SSIM 1.0000, PSNR lossless");
+    }
+
+    #[test]
+    fn differences_lower_quality() {
+        let pixels = gradient(16, 16);
+        let noisy: Vec<[u8; 4]> = pixels
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let d = if i % 2 == 0 { 40 } else { 0 };
+                [p[0].saturating_add(d), p[1], p[2], p[3]]
+            })
+            .collect();
+        assert!(ssim(&pixels, &noisy, 16) < 1.0);
+        let psnr = psnr(&pixels, &noisy);
+        assert!(psnr.is_finite() && psnr > 0.0, "{}", psnr);
+    }
+
+    #[test]
+    fn palette_never_exceeds_max_colors() {
+        let pixels = gradient(32, 32);
+        let histogram = histogram(&pixels);
+        assert_eq!(histogram.len(), 32 * 32);
+
+        for (max, dithering) in [(2, 0.0), (16, 1.0), (256, 0.5)] {
+            let result = attempt(&pixels, 32, &histogram, max, dithering);
+            assert!(result.palette.len() <= max, "{}", result.palette.len());
+            assert_eq!(result.quality.colors, result.palette.len());
+            assert_eq!(result.indices.len(), pixels.len());
+            assert!(result
+                .indices
+                .iter()
+                .all(|&i| (i as usize) < result.palette.len()));
+        }
+    }
+
+    #[test]
+    fn few_colors_round_trip_exactly() {
+        let palette = [
+            [255, 0, 0, 255],
+            [0, 255, 0, 255],
+            [0, 0, 255, 128],
+            [10, 20, 30, 255],
+            [0, 0, 0, 0],
+        ];
+        let pixels: Vec<[u8; 4]> = (0..64).map(|i| palette[(i * 7 / 3) % 5]).collect();
+        let histogram = histogram(&pixels);
+
+        for max in [5, 8, 256] {
+            let result = attempt(&pixels, 8, &histogram, max, 1.0);
+            assert_eq!(result.palette.len(), 5);
+            assert_eq!(colors(&result.palette, &result.indices), pixels);
+            assert_eq!(result.quality.psnr, f64::INFINITY);
+        }
+    }
+
+    #[test]
+    fn transparent_pixels_share_one_color() {
+        assert_eq!(normalize([12, 34, 56, 0]), [0; 4]);
+        assert_eq!(normalize([12, 34, 56, 1]), [12, 34, 56, 1]);
+    }
+
+    #[test]
+    fn validates_settings() {
+        assert_eq!(Quantize::default().validate(), Ok(()));
+
+        let invalid = [
+            Quantize {
+                quality_min: 90,
+                quality_max: 80,
+                ..Default::default()
+            },
+            Quantize {
+                quality_max: 101,
+                ..Default::default()
+            },
+            Quantize {
+                max_colors: 1,
+                ..Default::default()
+            },
+            Quantize {
+                max_colors: 257,
+                ..Default::default()
+            },
+            Quantize {
+                dithering: 1.5,
+                ..Default::default()
+            },
+        ];
+        for settings in invalid {
+            assert!(settings.validate().is_err(), "{:?}", settings);
+        }
+        assert_eq!(
+            Quantize {
+                quality_min: 90,
+                quality_max: 80,
+                ..Default::default()
+            }
+            .validate(),
+            Err("Invalid quality range: 90-80".to_string())
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use oxipng::{Deflaters, IndexSet, Interlacing, Options, RowFilter, StripChunks};
+use std::num::NonZeroU8;
+use std::time::Duration;
+
+// 元数据块的去除方式
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StripMode {
+    #[default]
+    None,
+    // 去除不影响显示的块
+    Safe,
+    // 去除全部非关键块
+    All,
+    // 去除除列表以外的全部非关键块
+    Keep,
+}
+
+impl StripMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(StripMode::None),
+            "safe" => Some(StripMode::Safe),
+            "all" => Some(StripMode::All),
+            "keep" => Some(StripMode::Keep),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum InterlaceMode {
+    // 保持原样
+    Keep,
+    #[default]
+    Off,
+    Adam7,
+}
+
+impl InterlaceMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "keep" => Some(InterlaceMode::Keep),
+            "off" => Some(InterlaceMode::Off),
+            "adam7" => Some(InterlaceMode::Adam7),
+            _ => None,
+        }
+    }
+
+    fn to_option(self) -> Option<Interlacing> {
+        match self {
+            InterlaceMode::Keep => None,
+            InterlaceMode::Off => Some(Interlacing::None),
+            InterlaceMode::Adam7 => Some(Interlacing::Adam7),
+        }
+    }
+}
+
+// 压缩器，Preset 表示使用预设自带的 libdeflate 级别
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Deflater {
+    #[default]
+    Preset,
+    Libdeflate(u8),
+    Zopfli(u8),
+}
+
+// 可在高级选项中勾选的行过滤器
+pub const FILTERS: [(RowFilter, &str); 10] = [
+    (RowFilter::None, "None"),
+    (RowFilter::Sub, "Sub"),
+    (RowFilter::Up, "Up"),
+    (RowFilter::Average, "Average"),
+    (RowFilter::Paeth, "Paeth"),
+    (RowFilter::MinSum, "MinSum"),
+    (RowFilter::Entropy, "Entropy"),
+    (RowFilter::Bigrams, "Bigrams"),
+    (RowFilter::BigEnt, "BigEnt"),
+    (RowFilter::Brute, "Brute"),
+];
+
+// 在预设基础上覆盖的 oxipng 选项
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub strip: StripMode,
+    // Keep 模式保留的块，逗号分隔，如 "iCCP,sRGB"
+    pub keep_chunks: String,
+    pub interlace: InterlaceMode,
+    pub bit_depth_reduction: bool,
+    pub color_type_reduction: bool,
+    pub palette_reduction: bool,
+    pub grayscale_reduction: bool,
+    // 允许改写完全透明像素的颜色以提高压缩率
+    pub optimize_alpha: bool,
+    // None 表示使用预设的过滤器组合
+    pub filters: Option<Vec<RowFilter>>,
+    pub deflater: Deflater,
+    // 超时（秒），0 表示不限制
+    pub timeout_secs: u64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            strip: StripMode::None,
+            keep_chunks: String::new(),
+            interlace: InterlaceMode::Off,
+            bit_depth_reduction: true,
+            color_type_reduction: true,
+            palette_reduction: true,
+            grayscale_reduction: true,
+            optimize_alpha: false,
+            filters: None,
+            deflater: Deflater::Preset,
+            timeout_secs: 0,
+        }
+    }
+}
+
+fn parse_chunks(text: &str) -> Result<IndexSet<[u8; 4]>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            <[u8; 4]>::try_from(name.as_bytes())
+                .ok()
+                .filter(|bytes| bytes.iter().all(u8::is_ascii_alphabetic))
+                .ok_or_else(|| format!("Invalid chunk name: {}", name))
+        })
+        .collect()
+}
+
+impl Settings {
+    pub fn to_options(&self, preset: u8) -> Result<Options, String> {
+        let mut options = Options::from_preset(preset);
+
+        options.strip = match self.strip {
+            StripMode::None => StripChunks::None,
+            StripMode::Safe => StripChunks::Safe,
+            StripMode::All => StripChunks::All,
+            StripMode::Keep => StripChunks::Keep(parse_chunks(&self.keep_chunks)?),
+        };
+        options.interlace = self.interlace.to_option();
+        options.bit_depth_reduction = self.bit_depth_reduction;
+        options.color_type_reduction = self.color_type_reduction;
+        options.palette_reduction = self.palette_reduction;
+        options.grayscale_reduction = self.grayscale_reduction;
+        options.optimize_alpha = self.optimize_alpha;
+
+        if let Some(filters) = &self.filters {
+            if filters.is_empty() {
+                return Err("Select at least one filter".to_string());
+            }
+            options.filter = filters.iter().copied().collect();
+        }
+        match self.deflater {
+            Deflater::Preset => {}
+            Deflater::Libdeflate(level) => {
+                options.deflate = Deflaters::Libdeflater {
+                    compression: level.clamp(0, 12),
+                }
+            }
+            Deflater::Zopfli(iterations) => {
+                options.deflate = Deflaters::Zopfli {
+                    iterations: NonZeroU8::new(iterations).unwrap_or(NonZeroU8::MIN),
+                }
+            }
+        }
+        if self.timeout_secs > 0 {
+            options.timeout = Some(Duration::from_secs(self.timeout_secs));
+        }
+
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_settings_keep_the_preset() {
+        let options = Settings::default().to_options(2).unwrap();
+        let preset = Options::from_preset(2);
+        assert_eq!(options.filter, preset.filter);
+        assert!(matches!(options.deflate, Deflaters::Libdeflater { .. }));
+        assert!(matches!(options.strip, StripChunks::None));
+        assert_eq!(options.interlace, Some(Interlacing::None));
+        assert_eq!(options.timeout, None);
+        assert!(!options.optimize_alpha);
+    }
+
+    #[test]
+    fn overrides_preset_options() {
+        let settings = Settings {
+            strip: StripMode::Keep,
+            keep_chunks: "iCCP, sRGB".to_string(),
+            interlace: InterlaceMode::Keep,
+            palette_reduction: false,
+            optimize_alpha: true,
+            filters: Some(vec![RowFilter::Paeth, RowFilter::None]),
+            deflater: Deflater::Zopfli(0),
+            timeout_secs: 30,
+            ..Default::default()
+        };
+
+        let options = settings.to_options(6).unwrap();
+        match options.strip {
+            StripChunks::Keep(chunks) => {
+                assert_eq!(chunks.into_iter().collect::<Vec<_>>(), [*b"iCCP", *b"sRGB"])
+            }
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(options.interlace, None);
+        assert!(!options.palette_reduction);
+        assert!(options.optimize_alpha);
+        assert_eq!(
+            options.filter.into_iter().collect::<Vec<_>>(),
+            [RowFilter::Paeth, RowFilter::None]
+        );
+        // 迭代次数至少为 1
+        assert!(matches!(
+            options.deflate,
+            Deflaters::Zopfli { iterations } if iterations.get() == 1
+        ));
+        assert_eq!(options.timeout, Some(Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn clamps_libdeflate_level() {
+        let settings = Settings {
+            deflater: Deflater::Libdeflate(20),
+            ..Default::default()
+        };
+        assert!(matches!(
+            settings.to_options(2).unwrap().deflate,
+            Deflaters::Libdeflater { compression: 12 }
+        ));
+    }
+
+    #[test]
+    fn rejects_empty_filter_list() {
+        let settings = Settings {
+            filters: Some(Vec::new()),
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.to_options(2).unwrap_err(),
+            "Select at least one filter"
+        );
+    }
+
+    #[test]
+    fn parses_chunk_names() {
+        let chunks = parse_chunks(" tEXt,,iCCP , tEXt").unwrap();
+        assert_eq!(chunks.into_iter().collect::<Vec<_>>(), [*b"tEXt", *b"iCCP"]);
+        assert!(parse_chunks("").unwrap().is_empty());
+
+        assert_eq!(
+            parse_chunks("iCCP,text1").unwrap_err(),
+            "Invalid chunk name: text1"
+        );
+        assert!(parse_chunks("ab").is_err());
+        assert!(parse_chunks("ab1d").is_err());
+        assert!(parse_chunks("é12").is_err());
+
+        let settings = Settings {
+            strip: StripMode::Keep,
+            keep_chunks: "bad!".to_string(),
+            ..Default::default()
+        };
+        assert!(settings.to_options(2).is_err());
+    }
+}
@@ -0,0 +1,122 @@
+// 无损优化后的逐像素校验
+fn decode(data: &[u8]) -> Result<image::ImageBuffer<image::Rgba<u16>, Vec<u16>>, String> {
+    image::load_from_memory_with_format(data, image::ImageFormat::Png)
+        .map(|image| image.to_rgba16())
+        .map_err(|e| e.to_string())
+}
+
+// 以 RGBA16 比较两份 PNG 数据，ignore_transparent 时不比较完全透明像素的颜色
+// （oxipng 的 optimize_alpha 会改写这些颜色）。不一致时返回差异说明
+pub fn compare(original: &[u8], optimized: &[u8], ignore_transparent: bool) -> Result<(), String> {
+    if original == optimized {
+        return Ok(());
+    }
+    let before = decode(original).map_err(|e| format!("Unable to decode original: {}", e))?;
+    let after = decode(optimized).map_err(|e| format!("Unable to decode output: {}", e))?;
+    if before.dimensions() != after.dimensions() {
+        return Err(format!(
+            "Output size {}x{} differs from {}x{}",
+            after.width(),
+            after.height(),
+            before.width(),
+            before.height()
+        ));
+    }
+
+    let mut first = None;
+    let mut count = 0usize;
+    for ((x, y, a), b) in before.enumerate_pixels().zip(after.pixels()) {
+        let same = if ignore_transparent && a.0[3] == 0 {
+            b.0[3] == 0
+        } else {
+            a == b
+        };
+        if !same {
+            first.get_or_insert((x, y));
+            count += 1;
+        }
+    }
+    match first {
+        None => Ok(()),
+        Some((x, y)) => Err(format!(
+            "{} pixel(s) differ from the original, first at ({}, {})",
+            count, x, y
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageBuffer, Rgba};
+
+    fn encode(image: DynamicImage) -> Vec<u8> {
+        let mut data = std::io::Cursor::new(Vec::new());
+        image.write_to(&mut data, image::ImageFormat::Png).unwrap();
+        data.into_inner()
+    }
+
+    fn png8(width: u32, pixels: &[[u8; 4]]) -> Vec<u8> {
+        let raw = pixels.iter().flatten().copied().collect();
+        let height = pixels.len() as u32 / width;
+        encode(DynamicImage::ImageRgba8(
+            ImageBuffer::from_raw(width, height, raw).unwrap(),
+        ))
+    }
+
+    fn png16(width: u32, pixels: &[[u16; 4]]) -> Vec<u8> {
+        let raw = pixels.iter().flatten().copied().collect();
+        let height = pixels.len() as u32 / width;
+        encode(DynamicImage::ImageRgba16(
+            ImageBuffer::<Rgba<u16>, Vec<u16>>::from_raw(width, height, raw).unwrap(),
+        ))
+    }
+
+    #[test]
+    fn transparent_colors_are_ignored_only_when_allowed() {
+        let original = png8(2, &[[10, 20, 30, 0], [1, 2, 3, 255]]);
+        let optimized = png8(2, &[[0, 0, 0, 0], [1, 2, 3, 255]]);
+
+        assert_eq!(compare(&original, &optimized, true), Ok(()));
+        assert_eq!(
+            compare(&original, &optimized, false),
+            Err("1 pixel(s) differ from the original, first at (0, 0)".to_string())
+        );
+
+        // 透明像素变为不透明始终算作差异
+        let opaque = png8(2, &[[10, 20, 30, 1], [1, 2, 3, 255]]);
+        assert!(compare(&original, &opaque, true).is_err());
+    }
+
+    #[test]
+    fn sixteen_bit_input_compares_at_full_depth() {
+        let eight = png8(2, &[[0, 128, 255, 255], [1, 2, 3, 4]]);
+        // 8 位值 v 对应 16 位的 v * 257
+        let exact = png16(2, &[[0, 128 * 257, 65535, 65535], [257, 514, 771, 1028]]);
+        assert_eq!(compare(&exact, &eight, false), Ok(()));
+
+        // 低字节不同的 16 位图像降为 8 位后会丢失精度
+        let precise = png16(
+            2,
+            &[[0, 128 * 257 + 1, 65535, 65535], [257, 514, 771, 1028]],
+        );
+        assert_eq!(
+            compare(&precise, &eight, false),
+            Err("1 pixel(s) differ from the original, first at (0, 0)".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_size_and_decode_errors() {
+        let one = png8(1, &[[0, 0, 0, 255]]);
+        let two = png8(2, &[[0, 0, 0, 255], [0, 0, 0, 255]]);
+        assert_eq!(
+            compare(&one, &two, false),
+            Err("Output size 2x1 differs from 1x1".to_string())
+        );
+        assert!(compare(&one, b"not a png", false)
+            .unwrap_err()
+            .starts_with("Unable to decode output"));
+        assert_eq!(compare(b"same", b"same", false), Ok(()));
+    }
+}